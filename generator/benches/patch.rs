@@ -0,0 +1,71 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use generator::{
+    construct_mesh, ferguson_patch_col, ferguson_patch_pt, preset, Axis, ColorAxis, Mesh,
+};
+
+fn pastel() -> Mesh {
+    preset::builtin()
+        .into_iter()
+        .find(|preset| preset.name == "Pastel")
+        .unwrap()
+        .mesh
+}
+
+/// A 100x100 grid on one patch, evaluated point by point and in batch.
+fn evaluate(c: &mut Criterion) {
+    let mesh = pastel();
+    let params: Vec<f32> = (0..=100).map(|i| i as f32 / 100.0).collect();
+
+    let x_coeff = mesh.patch_coefficients(1, 1, Axis::X);
+    let y_coeff = mesh.patch_coefficients(1, 1, Axis::Y);
+    let r_coeff = mesh.patch_color_coefficients(1, 1, ColorAxis::R);
+    let g_coeff = mesh.patch_color_coefficients(1, 1, ColorAxis::G);
+    let b_coeff = mesh.patch_color_coefficients(1, 1, ColorAxis::B);
+
+    let mut group = c.benchmark_group("evaluate 100x100");
+
+    group.bench_function("ferguson_patch_pt/col", |b| {
+        b.iter(|| {
+            for &u in &params {
+                for &v in &params {
+                    black_box(ferguson_patch_pt(u, v, &x_coeff, &y_coeff));
+                    black_box(ferguson_patch_col(u, v, (&r_coeff, &g_coeff, &b_coeff)));
+                }
+            }
+        })
+    });
+
+    group.bench_function("Patch::point/color", |b| {
+        let patch = mesh.patch(1, 1);
+
+        b.iter(|| {
+            for &u in &params {
+                for &v in &params {
+                    black_box(patch.point(u, v));
+                    black_box(patch.color(u, v));
+                }
+            }
+        })
+    });
+
+    group.bench_function("Patch::evaluate_grid", |b| {
+        let patch = mesh.patch(1, 1);
+        b.iter(|| black_box(patch.evaluate_grid(&params, &params)))
+    });
+
+    group.finish();
+}
+
+fn construct(c: &mut Criterion) {
+    let mesh = pastel();
+
+    c.bench_function("construct_mesh 5x5 subdivs 30", |b| {
+        b.iter(|| black_box(construct_mesh(&mesh, 30)))
+    });
+}
+
+criterion_group!(benches, evaluate, construct);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use generator::document::{baked_json_with, DocumentError};
+use generator::{Attributes, Mesh};
+
+/// "Save mesh" running on a background thread, so large meshes don't freeze the window.
+pub struct Baking {
+    path: PathBuf,
+    patches: usize,
+    progress: Arc<AtomicUsize>,
+    handle: JoinHandle<Result<(), DocumentError>>,
+}
+
+impl Baking {
+    pub fn start(
+        mesh: Mesh,
+        subdivs: usize,
+        aspect: f32,
+        attributes: Attributes,
+        path: PathBuf,
+    ) -> Self {
+        let patches = (mesh.width - 1) * (mesh.height - 1);
+        let progress = Arc::new(AtomicUsize::new(0));
+
+        let handle = {
+            let progress = progress.clone();
+            let path = path.clone();
+
+            thread::spawn(move || {
+                let json = baked_json_with(&mesh, subdivs, aspect, attributes, &progress);
+                serde_json::to_writer(BufWriter::new(File::create(path)?), &json)?;
+
+                Ok(())
+            })
+        };
+
+        Self {
+            path,
+            patches,
+            progress,
+            handle,
+        }
+    }
+
+    /// Share of patches tessellated so far, in `[0, 1]`. Writing the file comes after that.
+    pub fn progress(&self) -> f32 {
+        self.progress.load(Ordering::Relaxed) as f32 / self.patches as f32
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Waits for the thread and describes the outcome.
+    pub fn finish(self) -> String {
+        match self.handle.join() {
+            Ok(Ok(())) => format!("Saved {}", self.path.display()),
+            Ok(Err(err)) => format!("Saving {} failed: {err}", self.path.display()),
+            Err(_) => format!("Saving {} failed", self.path.display()),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+
+use generator::document::{baked_json_with, Document, DocumentError};
+use generator::fold::folded_patches;
+use generator::pdf::pdf;
+use generator::{Attributes, MAX_SUBDIVS};
+
+const USAGE: &str =
+    "[--subdivs <n>] [--attributes <list>] <document.json> <output.json | output.pdf>";
+
+/// Reports an error about `subject` (the program or a file) and exits.
+fn fail(subject: &str, err: impl Display) -> ! {
+    eprintln!("{subject}: error: {err}");
+    std::process::exit(1);
+}
+
+/// Comma separated `uv`, `patch_id`, `position_derivatives`, `color_derivatives` or `all`.
+fn parse_attributes(list: &str) -> Result<Attributes, String> {
+    let mut attributes = Attributes::default();
+
+    for name in list.split(',').map(str::trim) {
+        match name {
+            "uv" => attributes.uv = true,
+            "patch_id" => attributes.patch_id = true,
+            "position_derivatives" => attributes.position_derivatives = true,
+            "color_derivatives" => attributes.color_derivatives = true,
+            "all" => attributes = Attributes::ALL,
+            _ => return Err(format!("unknown attribute `{name}`")),
+        }
+    }
+
+    Ok(attributes)
+}
+
+fn parse_subdivs(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(subdivs) if subdivs <= MAX_SUBDIVS => Ok(subdivs),
+        _ => Err(format!(
+            "subdivs must be a number from 0 to {MAX_SUBDIVS}, not `{value}`"
+        )),
+    }
+}
+
+fn write_json(path: &str, json: &serde_json::Value) -> Result<(), DocumentError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, json)?;
+    // Dropping the writer would swallow errors of the last write.
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Tessellates a generator document into the JSON mesh read by the visualizer,
+/// or converts it to a PDF shading when the output ends with `.pdf`.
+pub fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let program = args[0].as_str();
+
+    let mut subdivs = 0;
+    let mut attributes = Attributes::default();
+    let mut rest = &args[1..];
+
+    loop {
+        match rest {
+            [flag, value, tail @ ..] if flag == "--subdivs" => {
+                subdivs = parse_subdivs(value).unwrap_or_else(|err| fail(program, err));
+                rest = tail;
+            }
+            [flag, value, tail @ ..] if flag == "--attributes" => {
+                attributes = parse_attributes(value).unwrap_or_else(|err| fail(program, err));
+                rest = tail;
+            }
+            _ => break,
+        }
+    }
+
+    let [input, output] = rest else {
+        fail(program, format!("usage: {program} {USAGE}"));
+    };
+
+    let Document { mesh, aspect, .. } =
+        Document::load(input).unwrap_or_else(|err| fail(input, err));
+
+    // Folded patches still bake, but the visualizer culls their back-facing triangles.
+    for (w, h, fold) in folded_patches(&mesh) {
+        eprintln!("{input}: warning: patch ({w}, {h}) {fold}");
+    }
+
+    let is_pdf = Path::new(output)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+
+    let written = if is_pdf {
+        std::fs::write(output, pdf(&mesh, subdivs)).map_err(DocumentError::from)
+    } else {
+        let json = baked_json_with(&mesh, subdivs, aspect, attributes, &AtomicUsize::new(0));
+        write_json(output, &json)
+    };
+
+    if let Err(err) = written {
+        fail(output, err);
+    }
+
+    println!(
+        "{output}: {} patches, subdivision {subdivs}",
+        (mesh.width - 1) * (mesh.height - 1)
+    );
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{ControlPoint, Mesh};
+
+/// Limits where a control point can move, so the outer ring of points keeps the mesh
+/// covering its frame.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Constraint {
+    #[default]
+    Free,
+    /// A frame corner - the point stays put and both boundary curves leave it straight.
+    Pinned(na::Vector2<f32>),
+    /// Slides along a horizontal frame edge at the given y, between the corners.
+    Horizontal(f32),
+    /// Slides along a vertical frame edge at the given x, between the corners.
+    Vertical(f32),
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::Free => write!(f, "free"),
+            Constraint::Pinned(at) => write!(f, "pinned at ({}, {})", at.x, at.y),
+            Constraint::Horizontal(y) => write!(f, "slides along y = {y}"),
+            Constraint::Vertical(x) => write!(f, "slides along x = {x}"),
+        }
+    }
+}
+
+impl Constraint {
+    pub fn position(&self, position: na::Vector2<f32>) -> na::Vector2<f32> {
+        match *self {
+            Constraint::Free => position,
+            Constraint::Pinned(at) => at,
+            Constraint::Horizontal(y) => na::Vector2::new(position.x.clamp(0.0, 1.0), y),
+            Constraint::Vertical(x) => na::Vector2::new(x, position.y.clamp(0.0, 1.0)),
+        }
+    }
+
+    /// `(u_tangent, v_tangent)` with the components leading off a frame edge removed,
+    /// so boundary curves stay straight and don't double back past their end points.
+    pub fn tangents(
+        &self,
+        (mut u_tangent, mut v_tangent): (na::Vector2<f32>, na::Vector2<f32>),
+    ) -> (na::Vector2<f32>, na::Vector2<f32>) {
+        let (horizontal, vertical) = match self {
+            Constraint::Free => (false, false),
+            Constraint::Pinned(_) => (true, true),
+            Constraint::Horizontal(_) => (true, false),
+            Constraint::Vertical(_) => (false, true),
+        };
+
+        if horizontal {
+            u_tangent = na::Vector2::new(u_tangent.x.max(0.0), 0.0);
+        }
+
+        if vertical {
+            v_tangent = na::Vector2::new(0.0, v_tangent.y.max(0.0));
+        }
+
+        (u_tangent, v_tangent)
+    }
+
+    pub fn apply(self, point: &mut ControlPoint) {
+        point.position = self.position(point.position);
+        (point.u_tangent, point.v_tangent) = self.tangents((point.u_tangent, point.v_tangent));
+    }
+}
+
+impl Mesh {
+    /// Pins the corners to the unit square and lets the other boundary points slide only along its edges.
+    /// Boundary points are moved onto the frame right away.
+    pub fn lock_boundary(&mut self) {
+        for h in 0..self.height {
+            for w in 0..self.width {
+                let x = (w == 0)
+                    .then_some(0.0)
+                    .or((w == self.width - 1).then_some(1.0));
+                let y = (h == 0)
+                    .then_some(0.0)
+                    .or((h == self.height - 1).then_some(1.0));
+
+                let constraint = match (x, y) {
+                    (Some(x), Some(y)) => Constraint::Pinned(na::Vector2::new(x, y)),
+                    (None, Some(y)) => Constraint::Horizontal(y),
+                    (Some(x), None) => Constraint::Vertical(x),
+                    (None, None) => Constraint::Free,
+                };
+
+                let point = &mut self.points[h * self.width + w];
+                point.constraint = constraint;
+                constraint.apply(point);
+            }
+        }
+    }
+
+    pub fn unlock_boundary(&mut self) {
+        for point in &mut self.points {
+            point.constraint = Constraint::Free;
+        }
+    }
+
+    pub fn is_boundary_locked(&self) -> bool {
+        self.points
+            .iter()
+            .any(|point| point.constraint != Constraint::Free)
+    }
+}
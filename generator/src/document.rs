@@ -0,0 +1,161 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+
+use serde::{Deserialize, Serialize};
+
+use crate::reference::Underlay;
+use crate::{construct_mesh_with_attributes, Attributes, Mesh};
+
+/// Everything the generator saves - the mesh plus editor state which belongs to it.
+///
+/// The mesh fields are stored at the top level, so a document can be read as a plain `Mesh`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document {
+    #[serde(flatten)]
+    pub mesh: Mesh,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underlay: Option<Underlay>,
+    /// Width / height ratio of the canvas the mesh is designed for, see `baked_json`.
+    #[serde(default = "default_aspect")]
+    pub aspect: f32,
+}
+
+fn default_aspect() -> f32 {
+    1.0
+}
+
+#[derive(Debug)]
+pub enum DocumentError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The number of points doesn't match the grid size, or the grid is smaller than a single patch.
+    InvalidGrid {
+        width: usize,
+        height: usize,
+        points: usize,
+    },
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocumentError::Io(err) => write!(f, "{err}"),
+            DocumentError::Json(err) => write!(f, "invalid document: {err}"),
+            DocumentError::InvalidGrid {
+                width,
+                height,
+                points,
+            } => write!(
+                f,
+                "invalid document: {points} points don't form a {width}x{height} grid of at least 2x2"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+impl From<io::Error> for DocumentError {
+    fn from(err: io::Error) -> Self {
+        DocumentError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for DocumentError {
+    fn from(err: serde_json::Error) -> Self {
+        DocumentError::Json(err)
+    }
+}
+
+impl Document {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DocumentError> {
+        let document: Document = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        let mesh = &document.mesh;
+
+        if mesh.width < 2 || mesh.height < 2 || mesh.points.len() != mesh.width * mesh.height {
+            return Err(DocumentError::InvalidGrid {
+                width: mesh.width,
+                height: mesh.height,
+                points: mesh.points.len(),
+            });
+        }
+
+        Ok(document)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), DocumentError> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+}
+
+impl Mesh {
+    /// Reads the mesh of a document, ignoring the rest of it.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DocumentError> {
+        Document::load(path).map(|document| document.mesh)
+    }
+
+    /// Writes the control points as an editable document.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), DocumentError> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+}
+
+/// Tessellated mesh in the JSON layout read by the visualizer. The unit square of the mesh is
+/// stretched over a canvas of `aspect`, which the visualizer can letterbox the mesh to.
+pub fn baked_json(mesh: &Mesh, subdivs: usize, aspect: f32) -> serde_json::Value {
+    baked_json_with(
+        mesh,
+        subdivs,
+        aspect,
+        Attributes::default(),
+        &AtomicUsize::new(0),
+    )
+}
+
+/// `baked_json` with the requested vertex `attributes` under `uvs`, `patch_ids`, `position_du`,
+/// `position_dv`, `color_du` and `color_dv`. Finished patches are counted in `progress`,
+/// see `construct_mesh_with_progress`.
+pub fn baked_json_with(
+    mesh: &Mesh,
+    subdivs: usize,
+    aspect: f32,
+    attributes: Attributes,
+    progress: &AtomicUsize,
+) -> serde_json::Value {
+    let ((positions, colors, indexes), extra) =
+        construct_mesh_with_attributes(mesh, subdivs, attributes, progress);
+
+    let mut json = serde_json::json!(
+        {
+            "positions": positions,
+            "colors": colors,
+            "indexes": indexes,
+            "aspect": aspect
+        }
+    );
+
+    if attributes.uv {
+        json["uvs"] = serde_json::json!(extra.uvs);
+    }
+
+    if attributes.patch_id {
+        json["patch_ids"] = serde_json::json!(extra.patch_ids);
+    }
+
+    if attributes.position_derivatives {
+        json["position_du"] = serde_json::json!(extra.position_du);
+        json["position_dv"] = serde_json::json!(extra.position_dv);
+    }
+
+    if attributes.color_derivatives {
+        json["color_du"] = serde_json::json!(extra.color_du);
+        json["color_dv"] = serde_json::json!(extra.color_dv);
+    }
+
+    json
+}
@@ -0,0 +1,50 @@
+use nalgebra::{self as na, SimdPartialOrd};
+
+/// Mouse movement is scaled down by this factor while the precision modifier is held.
+const PRECISION_SCALE: f32 = 0.1;
+
+/// A point being dragged with the mouse.
+///
+/// The point follows the absolute mouse position relative to where the drag started,
+/// so it never drifts away from the cursor because of accumulated per-frame deltas.
+/// Mouse positions are in mesh space, so zooming or panning mid-drag keeps the point under the cursor.
+pub struct Drag {
+    pub idx: usize,
+    anchor_mouse: na::Vector2<f32>,
+    anchor_position: na::Vector2<f32>,
+    precise: bool,
+}
+
+impl Drag {
+    pub fn new(idx: usize, mouse: na::Vector2<f32>, position: na::Vector2<f32>) -> Self {
+        Self {
+            idx,
+            anchor_mouse: mouse,
+            anchor_position: position,
+            precise: false,
+        }
+    }
+
+    /// Mesh space position of the dragged point for the current mouse position,
+    /// clamped to `bounds` (min, max corners).
+    pub fn target(
+        &mut self,
+        mouse: na::Vector2<f32>,
+        position: na::Vector2<f32>,
+        precise: bool,
+        bounds: (na::Vector2<f32>, na::Vector2<f32>),
+    ) -> na::Vector2<f32> {
+        // Switching precision mid-drag re-anchors at the current position,
+        // otherwise the point would jump by the scaled distance travelled so far.
+        if precise != self.precise {
+            self.anchor_mouse = mouse;
+            self.anchor_position = position;
+            self.precise = precise;
+        }
+
+        let scale = if precise { PRECISION_SCALE } else { 1.0 };
+        let delta = (mouse - self.anchor_mouse) * scale;
+
+        (self.anchor_position + delta).simd_clamp(bounds.0, bounds.1)
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+
+use nalgebra as na;
+
+use crate::{cubic_colvec, Derivatives, Mesh};
+
+/// Number of samples along each parameter when checking a patch.
+const SAMPLES: usize = 16;
+/// Jacobians this close to zero count as neither sign, so patches touching a cusp aren't flagged.
+const EPSILON: f32 = 1e-7;
+
+/// Why a patch doesn't map its domain onto the plane one-to-one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fold {
+    /// The whole patch is mirrored (or collapsed), its triangles face away.
+    Inverted,
+    /// The Jacobian changes sign inside the patch, part of it is folded over the rest.
+    Folded,
+    /// The patch keeps its orientation, but its boundary crosses itself.
+    SelfIntersecting,
+}
+
+impl fmt::Display for Fold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fold::Inverted => write!(f, "is inverted"),
+            Fold::Folded => write!(f, "folds over itself"),
+            Fold::SelfIntersecting => write!(f, "intersects itself"),
+        }
+    }
+}
+
+/// Checks the patch with top left corner at `(w, h)`.
+///
+/// The u parameter of `ferguson_patch_pt` runs down along y (the `v_tangent` direction) and v along x,
+/// so a well formed patch has a positive Jacobian `dx/dv * dy/du - dx/du * dy/dv` everywhere.
+pub fn patch_fold(mesh: &Mesh, w: usize, h: usize) -> Option<Fold> {
+    let patch = mesh.patch(w, h);
+
+    let mut positive = false;
+    let mut negative = false;
+
+    for i in 0..=SAMPLES {
+        for j in 0..=SAMPLES {
+            let u = i as f32 / SAMPLES as f32;
+            let v = j as f32 / SAMPLES as f32;
+
+            let Derivatives {
+                position_du: du,
+                position_dv: dv,
+                ..
+            } = patch.derivatives(u, v);
+
+            let jacobian = dv.x * du.y - du.x * dv.y;
+
+            positive |= jacobian > EPSILON;
+            negative |= jacobian < -EPSILON;
+        }
+    }
+
+    match (positive, negative) {
+        (true, true) => Some(Fold::Folded),
+        (false, _) => Some(Fold::Inverted),
+        (true, false) => boundary_intersects(&patch.x, &patch.y).then_some(Fold::SelfIntersecting),
+    }
+}
+
+/// All patches which are not one-to-one, as `(w, h, fold)`.
+pub fn folded_patches(mesh: &Mesh) -> Vec<(usize, usize, Fold)> {
+    let mut folds = Vec::new();
+
+    for w in 0..mesh.width - 1 {
+        for h in 0..mesh.height - 1 {
+            if let Some(fold) = patch_fold(mesh, w, h) {
+                folds.push((w, h, fold));
+            }
+        }
+    }
+
+    folds
+}
+
+/// Whether the closed boundary curve of the patch, approximated by a polyline, crosses itself.
+/// A boundary which doesn't, together with a Jacobian of constant sign, means the patch is one-to-one.
+fn boundary_intersects(x_acc: &na::Matrix4<f32>, y_acc: &na::Matrix4<f32>) -> bool {
+    let at = |u: f32, v: f32| {
+        let (u_vec, v_vec) = (cubic_colvec(u), cubic_colvec(v));
+        na::Vector2::new((x_acc * u_vec).dot(&v_vec), (y_acc * u_vec).dot(&v_vec))
+    };
+
+    let t = |i: usize| i as f32 / SAMPLES as f32;
+    let boundary: Vec<_> = (0..SAMPLES)
+        .map(|i| at(t(i), 0.0))
+        .chain((0..SAMPLES).map(|i| at(1.0, t(i))))
+        .chain((0..SAMPLES).map(|i| at(1.0 - t(i), 1.0)))
+        .chain((0..SAMPLES).map(|i| at(0.0, 1.0 - t(i))))
+        .collect();
+
+    let n = boundary.len();
+    let segment = |i: usize| (boundary[i], boundary[(i + 1) % n]);
+
+    for i in 0..n {
+        // Neighbouring segments share an end point, the last one wraps around to the first.
+        for j in i + 2..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+
+            if segments_cross(segment(i), segment(j)) {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Proper intersection test - segments only touching at an end point don't count.
+fn segments_cross(
+    (a, b): (na::Vector2<f32>, na::Vector2<f32>),
+    (c, d): (na::Vector2<f32>, na::Vector2<f32>),
+) -> bool {
+    let cross =
+        |o: na::Vector2<f32>, p: na::Vector2<f32>, q: na::Vector2<f32>| (p - o).perp(&(q - o));
+
+    let d1 = cross(a, b, c);
+    let d2 = cross(a, b, d);
+    let d3 = cross(c, d, a);
+    let d4 = cross(c, d, b);
+
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
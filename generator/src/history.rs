@@ -0,0 +1,250 @@
+use nalgebra as na;
+
+use generator::{ControlPoint, Mesh};
+
+/// A single reversible change to the mesh.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    MovePoint {
+        idx: usize,
+        from: na::Vector2<f32>,
+        to: na::Vector2<f32>,
+    },
+    SetColor {
+        idx: usize,
+        from: na::Vector3<f32>,
+        to: na::Vector3<f32>,
+    },
+    /// `(u_tangent, v_tangent)` pairs.
+    SetTangents {
+        idx: usize,
+        from: (na::Vector2<f32>, na::Vector2<f32>),
+        to: (na::Vector2<f32>, na::Vector2<f32>),
+    },
+    SetTwist {
+        idx: usize,
+        from: na::Vector2<f32>,
+        to: na::Vector2<f32>,
+    },
+    /// Changes several points at once, e.g. a transform of a selection.
+    SetPoints {
+        idxs: Vec<usize>,
+        from: Vec<ControlPoint>,
+        to: Vec<ControlPoint>,
+    },
+    /// Grid changes rebuild every point, so the whole mesh is kept.
+    ReplaceMesh { from: Box<Mesh>, to: Box<Mesh> },
+    /// Edits undone and redone together, e.g. a move and the colors resampled after it.
+    Group(Vec<Edit>),
+}
+
+impl Edit {
+    /// Moves the target values of the edit within the constraints of the points it changes.
+    fn constrain(&mut self, mesh: &Mesh) {
+        match self {
+            Edit::MovePoint { idx, to, .. } => *to = mesh.points[*idx].constraint.position(*to),
+            Edit::SetColor { .. }
+            | Edit::SetTwist { .. }
+            | Edit::ReplaceMesh { .. }
+            | Edit::Group(_) => {}
+            Edit::SetTangents { idx, to, .. } => *to = mesh.points[*idx].constraint.tangents(*to),
+            Edit::SetPoints { to, .. } => {
+                for point in to {
+                    point.constraint.apply(point);
+                }
+            }
+        }
+    }
+
+    fn apply(&self, mesh: &mut Mesh) {
+        match self {
+            Edit::MovePoint { idx, to, .. } => mesh.points[*idx].position = *to,
+            Edit::SetColor { idx, to, .. } => mesh.points[*idx].color = *to,
+            Edit::SetTangents { idx, to, .. } => {
+                mesh.points[*idx].u_tangent = to.0;
+                mesh.points[*idx].v_tangent = to.1;
+            }
+            Edit::SetTwist { idx, to, .. } => mesh.points[*idx].twist = *to,
+            Edit::SetPoints { idxs, to, .. } => {
+                for (idx, point) in idxs.iter().zip(to) {
+                    mesh.points[*idx] = point.clone();
+                }
+            }
+            Edit::ReplaceMesh { to, .. } => *mesh = (**to).clone(),
+            Edit::Group(edits) => {
+                for edit in edits {
+                    edit.apply(mesh);
+                }
+            }
+        }
+    }
+
+    fn revert(&self, mesh: &mut Mesh) {
+        match self {
+            Edit::MovePoint { idx, from, .. } => mesh.points[*idx].position = *from,
+            Edit::SetColor { idx, from, .. } => mesh.points[*idx].color = *from,
+            Edit::SetTangents { idx, from, .. } => {
+                mesh.points[*idx].u_tangent = from.0;
+                mesh.points[*idx].v_tangent = from.1;
+            }
+            Edit::SetTwist { idx, from, .. } => mesh.points[*idx].twist = *from,
+            Edit::SetPoints { idxs, from, .. } => {
+                for (idx, point) in idxs.iter().zip(from) {
+                    mesh.points[*idx] = point.clone();
+                }
+            }
+            Edit::ReplaceMesh { from, .. } => *mesh = (**from).clone(),
+            Edit::Group(edits) => {
+                for edit in edits.iter().rev() {
+                    edit.revert(mesh);
+                }
+            }
+        }
+    }
+
+    /// Folds `next` into `self` if both change the same property of the same point(s),
+    /// keeping the original starting value.
+    fn merge(&mut self, next: &Edit) -> bool {
+        match (self, next) {
+            (
+                Edit::MovePoint { idx, to, .. },
+                Edit::MovePoint {
+                    idx: next_idx,
+                    to: next_to,
+                    ..
+                },
+            ) if idx == next_idx => {
+                *to = *next_to;
+                true
+            }
+            (
+                Edit::SetColor { idx, to, .. },
+                Edit::SetColor {
+                    idx: next_idx,
+                    to: next_to,
+                    ..
+                },
+            ) if idx == next_idx => {
+                *to = *next_to;
+                true
+            }
+            (
+                Edit::SetTangents { idx, to, .. },
+                Edit::SetTangents {
+                    idx: next_idx,
+                    to: next_to,
+                    ..
+                },
+            ) if idx == next_idx => {
+                *to = *next_to;
+                true
+            }
+            (
+                Edit::SetPoints { idxs, to, .. },
+                Edit::SetPoints {
+                    idxs: next_idxs,
+                    to: next_to,
+                    ..
+                },
+            ) if idxs == next_idxs => {
+                to.clone_from(next_to);
+                true
+            }
+            // The gesture continues with the edit the group started with.
+            (Edit::Group(edits), next) => edits.first_mut().is_some_and(|first| first.merge(next)),
+            _ => false,
+        }
+    }
+}
+
+/// Undo / redo stacks of edits applied to a mesh.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /// Set while a continuous gesture (drag, slider) is in progress,
+    /// so its edits collapse into a single undo entry.
+    merging: bool,
+}
+
+impl History {
+    /// Applies `edit` to the mesh and records it as a separate undo entry.
+    pub fn apply(&mut self, mesh: &mut Mesh, mut edit: Edit) {
+        edit.constrain(mesh);
+        edit.apply(mesh);
+        self.redo.clear();
+        self.undo.push(edit);
+        self.merging = false;
+    }
+
+    /// Applies `edit` to the mesh, merging it with the previous entry
+    /// if both belong to the same continuous gesture.
+    pub fn apply_merged(&mut self, mesh: &mut Mesh, mut edit: Edit) {
+        edit.constrain(mesh);
+        edit.apply(mesh);
+        self.redo.clear();
+
+        let merged = self.merging
+            && self
+                .undo
+                .last_mut()
+                .map(|last| last.merge(&edit))
+                .unwrap_or(false);
+
+        if !merged {
+            self.undo.push(edit);
+        }
+
+        self.merging = true;
+    }
+
+    /// Applies `edit` as a consequence of the last undo entry, which then undoes both together.
+    pub fn amend(&mut self, mesh: &mut Mesh, mut edit: Edit) {
+        edit.constrain(mesh);
+        edit.apply(mesh);
+        self.redo.clear();
+
+        match self.undo.pop() {
+            Some(Edit::Group(mut edits)) => {
+                let merged = edits.last_mut().is_some_and(|last| last.merge(&edit));
+                if !merged {
+                    edits.push(edit);
+                }
+                self.undo.push(Edit::Group(edits));
+            }
+            Some(last) => self.undo.push(Edit::Group(vec![last, edit])),
+            None => self.undo.push(edit),
+        }
+    }
+
+    /// Ends the current continuous gesture - next edit starts a new undo entry.
+    pub fn end_merge(&mut self) {
+        self.merging = false;
+    }
+
+    pub fn undo(&mut self, mesh: &mut Mesh) -> bool {
+        self.merging = false;
+
+        match self.undo.pop() {
+            Some(edit) => {
+                edit.revert(mesh);
+                self.redo.push(edit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self, mesh: &mut Mesh) -> bool {
+        self.merging = false;
+
+        match self.redo.pop() {
+            Some(edit) => {
+                edit.apply(mesh);
+                self.undo.push(edit);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,644 @@
+pub mod constraint;
+pub mod document;
+pub mod fold;
+pub mod patch;
+pub mod pdf;
+pub mod preset;
+pub mod random;
+pub mod reference;
+pub mod sample;
+pub mod twist;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use nalgebra as na;
+use nalgebra::{matrix, vector};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use constraint::Constraint;
+use patch::{hermite_to_bezier, PatchKind};
+
+/// Highest subdivision count offered by the generator.
+pub const MAX_SUBDIVS: usize = 20;
+
+/// Grid of control points, `points[h * width + w]`. Neighbouring 2x2 blocks of points form the patches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mesh {
+    pub width: usize,
+    pub height: usize,
+    pub points: Vec<ControlPoint>,
+    #[serde(default)]
+    pub kind: PatchKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlPoint {
+    pub position: na::Vector2<f32>,
+    pub u_tangent: na::Vector2<f32>,
+    pub v_tangent: na::Vector2<f32>,
+    pub color: na::Vector3<f32>,
+    #[serde(default)]
+    pub constraint: Constraint,
+    /// Cross derivative of the position along both directions, shapes the patch interiors.
+    #[serde(default)]
+    pub twist: na::Vector2<f32>,
+    /// Cross derivative of the color along both directions.
+    #[serde(default)]
+    pub color_twist: na::Vector3<f32>,
+}
+
+impl ControlPoint {
+    pub fn new(
+        position: na::Vector2<f32>,
+        color: na::Vector3<f32>,
+        grid_w: usize,
+        grid_h: usize,
+    ) -> Self {
+        let u_tangent = na::Vector2::new(2.0 / (grid_w - 1) as f32, 0.0) * 0.5;
+        let v_tangent = na::Vector2::new(0.0, 2.0 / (grid_h - 1) as f32) * 0.5;
+
+        Self {
+            position,
+            u_tangent,
+            v_tangent,
+            color,
+            constraint: Constraint::Free,
+            twist: na::Vector2::zeros(),
+            color_twist: na::Vector3::zeros(),
+        }
+    }
+}
+
+impl Mesh {
+    pub fn new(width: usize, height: usize, colors: Vec<na::Vector3<f32>>) -> Self {
+        let mut count = 0;
+        let x_step = 1.0 / (width - 1) as f32;
+        let y_step = 1.0 / (height - 1) as f32;
+
+        let points = std::iter::from_fn(move || {
+            if count >= width * height {
+                None
+            } else {
+                let x = (count % width) as f32 * x_step;
+                let y = (count / width) as f32 * y_step;
+
+                let result = Some(ControlPoint::new(
+                    na::Vector2::new(x, y),
+                    colors[count],
+                    width,
+                    height,
+                ));
+                count += 1;
+                result
+            }
+        });
+
+        Self {
+            width,
+            height,
+            points: points.collect(),
+            kind: PatchKind::Hermite,
+        }
+    }
+
+    pub fn point_at(&self, w: usize, h: usize) -> &ControlPoint {
+        &self.points[h * self.width + w]
+    }
+
+    /// Regular grid of a new size, with colors bilinearly resampled from the current control points.
+    /// A locked boundary and the patch kind stay the same.
+    pub fn resized(&self, width: usize, height: usize) -> Self {
+        let colors = (0..width * height)
+            .map(|idx| {
+                let s = (idx % width) as f32 / (width - 1) as f32 * (self.width - 1) as f32;
+                let t = (idx / width) as f32 / (height - 1) as f32 * (self.height - 1) as f32;
+
+                let w = (s.floor() as usize).min(self.width - 2);
+                let h = (t.floor() as usize).min(self.height - 2);
+                let (fs, ft) = (s - w as f32, t - h as f32);
+
+                let c = |w, h| self.point_at(w, h).color;
+
+                c(w, h) * (1.0 - fs) * (1.0 - ft)
+                    + c(w + 1, h) * fs * (1.0 - ft)
+                    + c(w, h + 1) * (1.0 - fs) * ft
+                    + c(w + 1, h + 1) * fs * ft
+            })
+            .collect();
+
+        let mut mesh = Mesh::new(width, height, colors).with_kind(self.kind);
+
+        if self.is_boundary_locked() {
+            mesh.lock_boundary();
+        }
+
+        mesh
+    }
+}
+
+pub const H: na::Matrix4<f32> = matrix![
+     2.0, -3.0,  0.0,  1.0;
+    -2.0,  3.0,  0.0,  0.0;
+     1.0, -2.0,  1.0,  0.0;
+     1.0, -1.0,  0.0,  0.0;
+];
+
+/// Takes power basis coefficients (ordered as `cubic_colvec`) of a cubic to its Hermite vector
+/// `[p0, p1, t0, t1]`, the inverse of `H^T`.
+const POWER_TO_HERMITE: na::Matrix4<f32> = matrix![
+    0.0, 0.0, 0.0, 1.0;
+    1.0, 1.0, 1.0, 1.0;
+    0.0, 0.0, 1.0, 0.0;
+    3.0, 2.0, 1.0, 0.0;
+];
+
+pub fn cubic_colvec(v: f32) -> na::Vector4<f32> {
+    vector![v * v * v, v * v, v, 1.0]
+}
+
+/// Derivative of `cubic_colvec`.
+pub fn cubic_derivative(t: f32) -> na::Vector4<f32> {
+    vector![3.0 * t * t, 2.0 * t, 1.0, 0.0]
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColorAxis {
+    R,
+    G,
+    B,
+}
+
+pub fn geometric_coefficients(
+    p00: &ControlPoint,
+    p01: &ControlPoint,
+    p10: &ControlPoint,
+    p11: &ControlPoint,
+    axis: Axis,
+) -> na::Matrix4<f32> {
+    let l = |p: &ControlPoint| match axis {
+        Axis::X => p.position.x,
+        Axis::Y => p.position.y,
+    };
+
+    let u = |p: &ControlPoint| match axis {
+        Axis::X => p.u_tangent.x,
+        Axis::Y => p.u_tangent.y,
+    };
+
+    let v = |p: &ControlPoint| match axis {
+        Axis::X => p.v_tangent.x,
+        Axis::Y => p.v_tangent.y,
+    };
+
+    let t = |p: &ControlPoint| match axis {
+        Axis::X => p.twist.x,
+        Axis::Y => p.twist.y,
+    };
+
+    matrix![
+        l(p00), l(p01), v(p00), v(p01);
+        l(p10), l(p11), v(p10), v(p11);
+        u(p00), u(p01), t(p00), t(p01);
+        u(p10), u(p11), t(p10), t(p11);
+    ]
+    .transpose()
+}
+
+pub fn color_coefficients(
+    p00: &ControlPoint,
+    p01: &ControlPoint,
+    p10: &ControlPoint,
+    p11: &ControlPoint,
+    color: ColorAxis,
+) -> na::Matrix4<f32> {
+    let l = |p: &ControlPoint| match color {
+        ColorAxis::R => p.color.x,
+        ColorAxis::G => p.color.y,
+        ColorAxis::B => p.color.z,
+    };
+
+    let t = |p: &ControlPoint| match color {
+        ColorAxis::R => p.color_twist.x,
+        ColorAxis::G => p.color_twist.y,
+        ColorAxis::B => p.color_twist.z,
+    };
+
+    matrix![
+        l(p00), l(p01), 0.0, 0.0;
+        l(p10), l(p11), 0.0, 0.0;
+        0.0, 0.0, t(p00), t(p01);
+        0.0, 0.0, t(p10), t(p11);
+    ]
+    .transpose()
+}
+
+pub fn ferguson_patch_pt(
+    u: f32,
+    v: f32,
+    geom_x: &na::Matrix4<f32>,
+    geom_y: &na::Matrix4<f32>,
+) -> na::Vector2<f32> {
+    let u_vec = cubic_colvec(u);
+    let v_vec = cubic_colvec(v);
+
+    let x_acc = H.transpose() * geom_x.transpose() * H;
+    let y_acc = H.transpose() * geom_y.transpose() * H;
+
+    let ux = x_acc * u_vec;
+    let uy = y_acc * u_vec;
+
+    na::Vector2::new(ux.dot(&v_vec), uy.dot(&v_vec))
+}
+
+pub fn ferguson_patch_col(
+    u: f32,
+    v: f32,
+    rgb_coeffs: (&na::Matrix4<f32>, &na::Matrix4<f32>, &na::Matrix4<f32>),
+) -> na::Vector3<f32> {
+    let u_vec = cubic_colvec(u);
+    let v_vec = cubic_colvec(v);
+
+    let r_acc = H.transpose() * rgb_coeffs.0.transpose() * H;
+    let g_acc = H.transpose() * rgb_coeffs.1.transpose() * H;
+    let b_acc = H.transpose() * rgb_coeffs.2.transpose() * H;
+
+    let ur = r_acc * u_vec;
+    let ug = g_acc * u_vec;
+    let ub = b_acc * u_vec;
+
+    na::Vector3::new(ur.dot(&v_vec), ug.dot(&v_vec), ub.dot(&v_vec))
+}
+
+/// A patch ready for evaluation, with the products `H^T * G^T * H` of all its
+/// coordinates computed once instead of on every `ferguson_patch_pt` call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Patch {
+    pub x: na::Matrix4<f32>,
+    pub y: na::Matrix4<f32>,
+    pub r: na::Matrix4<f32>,
+    pub g: na::Matrix4<f32>,
+    pub b: na::Matrix4<f32>,
+}
+
+impl Patch {
+    pub fn new(
+        geom_x: &na::Matrix4<f32>,
+        geom_y: &na::Matrix4<f32>,
+        rgb_coeffs: (&na::Matrix4<f32>, &na::Matrix4<f32>, &na::Matrix4<f32>),
+    ) -> Self {
+        let acc = |coeffs: &na::Matrix4<f32>| H.transpose() * coeffs.transpose() * H;
+
+        Self {
+            x: acc(geom_x),
+            y: acc(geom_y),
+            r: acc(rgb_coeffs.0),
+            g: acc(rgb_coeffs.1),
+            b: acc(rgb_coeffs.2),
+        }
+    }
+
+    /// Same as `ferguson_patch_pt` with the coefficients of this patch.
+    pub fn point(&self, u: f32, v: f32) -> na::Vector2<f32> {
+        let (u_vec, v_vec) = (cubic_colvec(u), cubic_colvec(v));
+
+        na::Vector2::new((self.x * u_vec).dot(&v_vec), (self.y * u_vec).dot(&v_vec))
+    }
+
+    /// Same as `ferguson_patch_col` with the coefficients of this patch.
+    pub fn color(&self, u: f32, v: f32) -> na::Vector3<f32> {
+        let (u_vec, v_vec) = (cubic_colvec(u), cubic_colvec(v));
+
+        na::Vector3::new(
+            (self.r * u_vec).dot(&v_vec),
+            (self.g * u_vec).dot(&v_vec),
+            (self.b * u_vec).dot(&v_vec),
+        )
+    }
+
+    /// Derivatives of the position and color along u and v.
+    pub fn derivatives(&self, u: f32, v: f32) -> Derivatives {
+        let du = |acc: &na::Matrix4<f32>| (acc * cubic_derivative(u)).dot(&cubic_colvec(v));
+        let dv = |acc: &na::Matrix4<f32>| (acc * cubic_colvec(u)).dot(&cubic_derivative(v));
+
+        Derivatives {
+            position_du: na::Vector2::new(du(&self.x), du(&self.y)),
+            position_dv: na::Vector2::new(dv(&self.x), dv(&self.y)),
+            color_du: na::Vector3::new(du(&self.r), du(&self.g), du(&self.b)),
+            color_dv: na::Vector3::new(dv(&self.r), dv(&self.g), dv(&self.b)),
+        }
+    }
+
+    /// The part `[u, u + size] x [v, v + size]` of the patch, reparametrized to `[0, 1]^2`.
+    pub fn part(&self, u: f32, v: f32, size: f32) -> Patch {
+        // `cubic_colvec(start + size * t) = shift * cubic_colvec(t)`.
+        let shift = |start: f32| {
+            let (a, d) = (start, size);
+
+            matrix![
+                d * d * d, 3.0 * a * d * d, 3.0 * a * a * d, a * a * a;
+                0.0,       d * d,           2.0 * a * d,     a * a;
+                0.0,       0.0,             d,               a;
+                0.0,       0.0,             0.0,             1.0;
+            ]
+        };
+
+        let (u_shift, v_shift) = (shift(u), shift(v));
+        let restrict = |acc: &na::Matrix4<f32>| v_shift.transpose() * acc * u_shift;
+
+        Patch {
+            x: restrict(&self.x),
+            y: restrict(&self.y),
+            r: restrict(&self.r),
+            g: restrict(&self.g),
+            b: restrict(&self.b),
+        }
+    }
+
+    /// Bezier control nets of the x and y coordinates, in the layout of `patch::hermite_to_bezier`.
+    /// Rows step along u, columns along v.
+    pub fn bezier_net(&self) -> (na::Matrix4<f32>, na::Matrix4<f32>) {
+        // Undoes `H^T * G^T * H`, leaving the geometric coefficients.
+        let net = |acc: &na::Matrix4<f32>| {
+            hermite_to_bezier(&(POWER_TO_HERMITE * acc.transpose() * POWER_TO_HERMITE.transpose()))
+        };
+
+        (net(&self.x), net(&self.y))
+    }
+
+    /// Calls `f` with `[x, y, r, g, b]` at every `(u, v)` pair of the grid `us x vs`, u in the outer loop.
+    /// `u_basis` and `v_basis` give the cubic powers, or their derivatives.
+    ///
+    /// Each u reduces the five coordinates to rows of cubic coefficients in v, which are then
+    /// multiplied with the powers of all `vs` at once.
+    fn for_each_in_grid(
+        &self,
+        us: &[f32],
+        vs: &[f32],
+        u_basis: fn(f32) -> na::Vector4<f32>,
+        v_basis: fn(f32) -> na::Vector4<f32>,
+        mut f: impl FnMut(&[f32]),
+    ) {
+        let powers =
+            na::Matrix4xX::from_columns(&vs.iter().map(|&v| v_basis(v)).collect::<Vec<_>>());
+
+        for &u in us {
+            let u_vec = u_basis(u);
+            let rows = na::Matrix5x4::from_rows(&[
+                (self.x * u_vec).transpose(),
+                (self.y * u_vec).transpose(),
+                (self.r * u_vec).transpose(),
+                (self.g * u_vec).transpose(),
+                (self.b * u_vec).transpose(),
+            ]);
+
+            // Column-major, so every vertex is a run of five values.
+            (rows * &powers).as_slice().chunks_exact(5).for_each(&mut f);
+        }
+    }
+
+    /// Positions and colors at every `(u, v)` pair of the grid `us x vs`, u in the outer loop.
+    pub fn evaluate_grid(
+        &self,
+        us: &[f32],
+        vs: &[f32],
+    ) -> (Vec<na::Vector2<f32>>, Vec<na::Vector3<f32>>) {
+        let mut positions = Vec::with_capacity(us.len() * vs.len());
+        let mut colors = Vec::with_capacity(us.len() * vs.len());
+
+        self.for_each_in_grid(us, vs, cubic_colvec, cubic_colvec, |values| {
+            positions.push(na::Vector2::new(values[0], values[1]));
+            colors.push(na::Vector3::new(values[2], values[3], values[4]));
+        });
+
+        (positions, colors)
+    }
+
+    /// `derivatives` at every `(u, v)` pair of the grid `us x vs`, in the order of `evaluate_grid`.
+    pub fn derivative_grid(&self, us: &[f32], vs: &[f32]) -> Vec<Derivatives> {
+        let mut derivatives = Vec::with_capacity(us.len() * vs.len());
+
+        self.for_each_in_grid(us, vs, cubic_derivative, cubic_colvec, |values| {
+            derivatives.push(Derivatives {
+                position_du: na::Vector2::new(values[0], values[1]),
+                color_du: na::Vector3::new(values[2], values[3], values[4]),
+                ..Default::default()
+            });
+        });
+
+        let mut along_v = derivatives.iter_mut();
+        self.for_each_in_grid(us, vs, cubic_colvec, cubic_derivative, |values| {
+            let derivatives = along_v.next().unwrap();
+            derivatives.position_dv = na::Vector2::new(values[0], values[1]);
+            derivatives.color_dv = na::Vector3::new(values[2], values[3], values[4]);
+        });
+
+        derivatives
+    }
+}
+
+/// Partial derivatives at a point of a patch. u runs along h and v along w, as in `ferguson_patch_pt`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Derivatives {
+    pub position_du: na::Vector2<f32>,
+    pub position_dv: na::Vector2<f32>,
+    pub color_du: na::Vector3<f32>,
+    pub color_dv: na::Vector3<f32>,
+}
+
+/// Optional per-vertex outputs of `construct_mesh_with_attributes`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Attributes {
+    pub uv: bool,
+    pub patch_id: bool,
+    pub position_derivatives: bool,
+    pub color_derivatives: bool,
+}
+
+impl Attributes {
+    pub const ALL: Attributes = Attributes {
+        uv: true,
+        patch_id: true,
+        position_derivatives: true,
+        color_derivatives: true,
+    };
+}
+
+/// Per-vertex data next to positions and colors. Attributes which weren't requested stay empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VertexAttributes {
+    /// Parameters of the vertex within its patch, see `Derivatives`.
+    pub uvs: Vec<na::Vector2<f32>>,
+    /// Index of the patch with top left corner at `(w, h)` is `w * (height - 1) + h`.
+    pub patch_ids: Vec<u32>,
+    /// Position derivatives, scaled to normalized device coordinates like the positions.
+    pub position_du: Vec<na::Vector3<f32>>,
+    pub position_dv: Vec<na::Vector3<f32>>,
+    pub color_du: Vec<na::Vector3<f32>>,
+    pub color_dv: Vec<na::Vector3<f32>>,
+}
+
+impl VertexAttributes {
+    fn append(&mut self, other: VertexAttributes) {
+        self.uvs.extend(other.uvs);
+        self.patch_ids.extend(other.patch_ids);
+        self.position_du.extend(other.position_du);
+        self.position_dv.extend(other.position_dv);
+        self.color_du.extend(other.color_du);
+        self.color_dv.extend(other.color_dv);
+    }
+}
+
+/// Vertex positions, vertex colors and triangle indexes of a tessellated mesh.
+pub type Buffers = (Vec<na::Vector3<f32>>, Vec<na::Vector3<f32>>, Vec<u32>);
+
+/// Tessellates every patch into `(subdivs + 1)^2` quads. Positions are mapped from mesh space
+/// to normalized device coordinates, with y pointing up.
+pub fn construct_mesh(mesh: &Mesh, subdivs: usize) -> Buffers {
+    construct_mesh_with_progress(mesh, subdivs, &AtomicUsize::new(0))
+}
+
+/// `construct_mesh` which adds one to `progress` for every finished patch, so another thread can follow along.
+///
+/// With the `rayon` feature patches are tessellated in parallel. Each patch gets a fixed block
+/// of vertices and indexes, so the output is the same either way.
+pub fn construct_mesh_with_progress(
+    mesh: &Mesh,
+    subdivs: usize,
+    progress: &AtomicUsize,
+) -> Buffers {
+    construct_mesh_with_attributes(mesh, subdivs, Attributes::default(), progress).0
+}
+
+/// `construct_mesh_with_progress` which also outputs the requested `attributes` of every vertex.
+pub fn construct_mesh_with_attributes(
+    mesh: &Mesh,
+    subdivs: usize,
+    attributes: Attributes,
+    progress: &AtomicUsize,
+) -> (Buffers, VertexAttributes) {
+    // mesh with subdivs = 3
+    //  0  1  2  3  4
+    //  5  6  7  8  9
+    // 10 11 12 13 14
+
+    // indexes:
+    // 5 1 0
+    // 5 6 1
+    // 7 2 1
+    // 6 7 2
+    // 7 3 2
+    // 7 8 3
+    // 8 4 3
+    // 8 9 4
+    // 10 6 5
+    // 10 11 6
+    // 11 7 6
+    // 11 12 7
+    // 12 8 7
+    // 12 13 8
+    // 13 9 8
+    // 13 14 9
+
+    let steps = subdivs + 1;
+    let row_len = steps + 1;
+
+    let params: Vec<f32> = (0..=steps).map(|i| i as f32 / steps as f32).collect();
+
+    let patches: Vec<(usize, usize)> = (0..mesh.width - 1)
+        .flat_map(|w| (0..mesh.height - 1).map(move |h| (w, h)))
+        .collect();
+
+    let tessellate = |(patch_idx, &(w, h)): (usize, &(usize, usize))| {
+        let index_start = patch_idx * row_len * row_len;
+
+        let patch = mesh.patch(w, h);
+        let (points, colors) = patch.evaluate_grid(&params, &params);
+
+        let positions: Vec<_> = points
+            .into_iter()
+            .map(|mut p| {
+                p *= 2.0;
+                p -= na::Vector2::new(1.0, 1.0);
+                p.component_mul_assign(&na::Vector2::new(1.0, -1.0));
+
+                na::Vector3::new(p.x, p.y, 0.0)
+            })
+            .collect();
+
+        let mut indexes = Vec::with_capacity(steps * steps * 6);
+
+        for r in 0..steps {
+            for c in 0..steps {
+                indexes.push((index_start + r * row_len + c + row_len) as u32);
+                indexes.push((index_start + r * row_len + c + 1) as u32);
+                indexes.push((index_start + r * row_len + c) as u32);
+
+                indexes.push((index_start + r * row_len + c + row_len) as u32);
+                indexes.push((index_start + r * row_len + c + row_len + 1) as u32);
+                indexes.push((index_start + r * row_len + c + 1) as u32);
+            }
+        }
+
+        let mut extra = VertexAttributes::default();
+
+        if attributes.uv {
+            extra.uvs = params
+                .iter()
+                .flat_map(|&u| params.iter().map(move |&v| na::Vector2::new(u, v)))
+                .collect();
+        }
+
+        if attributes.patch_id {
+            extra.patch_ids = vec![patch_idx as u32; row_len * row_len];
+        }
+
+        if attributes.position_derivatives || attributes.color_derivatives {
+            let derivatives = patch.derivative_grid(&params, &params);
+            // Matches the mapping of positions into normalized device coordinates.
+            let ndc = |d: na::Vector2<f32>| na::Vector3::new(d.x * 2.0, d.y * -2.0, 0.0);
+
+            if attributes.position_derivatives {
+                extra.position_du = derivatives.iter().map(|d| ndc(d.position_du)).collect();
+                extra.position_dv = derivatives.iter().map(|d| ndc(d.position_dv)).collect();
+            }
+
+            if attributes.color_derivatives {
+                extra.color_du = derivatives.iter().map(|d| d.color_du).collect();
+                extra.color_dv = derivatives.iter().map(|d| d.color_dv).collect();
+            }
+        }
+
+        progress.fetch_add(1, Ordering::Relaxed);
+
+        (positions, colors, indexes, extra)
+    };
+
+    #[cfg(feature = "rayon")]
+    let tessellated: Vec<_> = patches.par_iter().enumerate().map(tessellate).collect();
+    #[cfg(not(feature = "rayon"))]
+    let tessellated: Vec<_> = patches.iter().enumerate().map(tessellate).collect();
+
+    let entries = patches.len() * row_len * row_len;
+
+    let mut positions = Vec::with_capacity(entries);
+    let mut colors = Vec::with_capacity(entries);
+    let mut indexes = Vec::with_capacity(patches.len() * steps * steps * 6);
+    let mut extra = VertexAttributes::default();
+
+    for (patch_positions, patch_colors, patch_indexes, patch_extra) in tessellated {
+        positions.extend(patch_positions);
+        colors.extend(patch_colors);
+        indexes.extend(patch_indexes);
+        extra.append(patch_extra);
+    }
+
+    ((positions, colors, indexes), extra)
+}
@@ -1,87 +1,41 @@
+mod baking;
+mod drag;
+mod history;
+mod preview;
+mod selection;
+mod snap;
+mod view;
+
 use std::time::SystemTime;
 
+use baking::Baking;
+use drag::Drag;
+use history::{Edit, History};
+use preview::Preview;
+use selection::Selection;
+use snap::Snapping;
+use view::View;
+
+use generator::document::Document;
+use generator::patch::PatchKind;
+use generator::pdf::pdf;
+use generator::preset::{self, Preset};
+use generator::random::{Harmony, Seed};
+use generator::reference::{ReferenceImage, Underlay};
+use generator::{Attributes, Axis, Mesh, Patch, MAX_SUBDIVS};
+use macroquad::models::{draw_mesh, Mesh as QuadMesh, Vertex};
 use macroquad::prelude::*;
 use macroquad::ui::{hash, root_ui};
 use miniquad::window::set_window_size;
-use nalgebra::{self as na, SimdPartialOrd};
-use nalgebra::{matrix, vector};
-
-#[derive(Debug)]
-struct Mesh {
-    width: usize,
-    height: usize,
-    points: Vec<ControlPoint>,
-}
-
-#[derive(Debug)]
-struct ControlPoint {
-    position: na::Vector2<f32>,
-    u_tangent: na::Vector2<f32>,
-    v_tangent: na::Vector2<f32>,
-    color: na::Vector3<f32>,
-}
-
-impl ControlPoint {
-    fn new(
-        position: na::Vector2<f32>,
-        color: na::Vector3<f32>,
-        grid_w: usize,
-        grid_h: usize,
-    ) -> Self {
-        let u_tangent = na::Vector2::new(2.0 / (grid_w - 1) as f32, 0.0) * 0.5;
-        let v_tangent = na::Vector2::new(0.0, 2.0 / (grid_h - 1) as f32) * 0.5;
-
-        Self {
-            position,
-            u_tangent,
-            v_tangent,
-            color,
-        }
-    }
-}
-
-impl Mesh {
-    fn new(width: usize, height: usize, colors: Vec<na::Vector3<f32>>) -> Self {
-        let mut count = 0;
-        let x_step = 1.0 / (width - 1) as f32;
-        let y_step = 1.0 / (height - 1) as f32;
-
-        let points = std::iter::from_fn(move || {
-            if count >= width * height {
-                None
-            } else {
-                let x = (count % width) as f32 * x_step;
-                let y = (count / width) as f32 * y_step;
-
-                let result = Some(ControlPoint::new(
-                    na::Vector2::new(x, y),
-                    colors[count as usize],
-                    width,
-                    height,
-                ));
-                count += 1;
-                result
-            }
-        });
-
-        Self {
-            width,
-            height,
-            points: points.collect(),
-        }
-    }
-
-    fn point_at(&self, w: usize, h: usize) -> &ControlPoint {
-        &self.points[(h * self.width + w) as usize]
-    }
-}
+use nalgebra as na;
+use nalgebra::vector;
 
-fn point_idx(mouse_pos: na::Vector2<f32>, mesh: &Mesh) -> Option<usize> {
-    if mouse_pos.x > WORKSPACE_SIZE_W || mouse_pos.y > WORKSPACE_SIZE_H {
+fn point_idx(mouse_pos: na::Vector2<f32>, mesh: &Mesh, view: &View) -> Option<usize> {
+    if !view.contains(mouse_pos) {
         None
     } else {
         for (idx, point) in mesh.points.iter().enumerate() {
-            let spoint = ws_coord(&point.position);
+            let spoint = view.ws_coord(&point.position);
 
             if (spoint - mouse_pos).norm() < 5.0 {
                 return Some(idx);
@@ -95,347 +49,414 @@ fn point_idx(mouse_pos: na::Vector2<f32>, mesh: &Mesh) -> Option<usize> {
 const UI_SIZE: f32 = 200.0;
 const WORKSPACE_SIZE_W: f32 = 600.0;
 const WORKSPACE_SIZE_H: f32 = 600.0;
-const WORKSPACE_PADDING: f32 = 160.0;
-
-fn ws_coord(point: &na::Vector2<f32>) -> na::Vector2<f32> {
-    let sw = WORKSPACE_SIZE_W - WORKSPACE_PADDING;
-    let sh = WORKSPACE_SIZE_H - WORKSPACE_PADDING;
-    point.component_mul(&na::Vector2::new(sw, sh))
-        + na::Vector2::new(WORKSPACE_PADDING / 2.0, WORKSPACE_PADDING / 2.0)
-}
-
-fn pt_coord(point: &na::Vector2<f32>) -> na::Vector2<f32> {
-    let sw = WORKSPACE_SIZE_W - WORKSPACE_PADDING;
-    let sh = WORKSPACE_SIZE_H - WORKSPACE_PADDING;
-
-    point
-        .component_div(&na::Vector2::new(sw, sh))
-        .simd_clamp(na::Vector2::new(0.0, 0.0), na::Vector2::new(1.0, 1.0))
-}
-
-const H: na::Matrix4<f32> = matrix![
-     2.0, -3.0,  0.0,  1.0;
-    -2.0,  3.0,  0.0,  0.0;
-     1.0, -2.0,  1.0,  0.0;
-     1.0, -1.0,  0.0,  0.0;
-];
-
-fn cubic_colvec(v: f32) -> na::Vector4<f32> {
-    vector![v * v * v, v * v, v, 1.0]
-}
-
-#[derive(PartialEq, Eq)]
-enum Axis {
-    X,
-    Y,
-}
-
-#[derive(PartialEq, Eq)]
-enum ColorAxis {
-    R,
-    G,
-    B,
-}
-
-fn geometric_coefficients(
-    p00: &ControlPoint,
-    p01: &ControlPoint,
-    p10: &ControlPoint,
-    p11: &ControlPoint,
-    axis: Axis,
-) -> na::Matrix4<f32> {
-    let l = |p: &ControlPoint| match axis {
-        Axis::X => p.position.x,
-        Axis::Y => p.position.y,
-    };
-
-    let u = |p: &ControlPoint| match axis {
-        Axis::X => p.u_tangent.x,
-        Axis::Y => p.u_tangent.y,
-    };
-
-    let v = |p: &ControlPoint| match axis {
-        Axis::X => p.v_tangent.x,
-        Axis::Y => p.v_tangent.y,
-    };
-
-    matrix![
-        l(p00), l(p01), v(p00), v(p01);
-        l(p10), l(p11), v(p10), v(p11);
-        u(p00), u(p01), 0.0, 0.0;
-        u(p10), u(p11), 0.0, 0.0;
-    ]
-    .transpose()
-}
-
-fn color_coefficients(
-    p00: &ControlPoint,
-    p01: &ControlPoint,
-    p10: &ControlPoint,
-    p11: &ControlPoint,
-    color: ColorAxis,
-) -> na::Matrix4<f32> {
-    let l = |p: &ControlPoint| match color {
-        ColorAxis::R => p.color.x,
-        ColorAxis::G => p.color.y,
-        ColorAxis::B => p.color.z,
+/// How close (in pixels) a dragged point has to get to something to snap to it.
+const SNAP_DISTANCE: f32 = 8.0;
+/// How far (in mesh space) points may overshoot the unit square with the extended canvas.
+const EXTENDED_CANVAS_MARGIN: f32 = 0.25;
+
+/// Range of mesh space positions points can be dragged to.
+fn drag_bounds(extended_canvas: bool) -> (na::Vector2<f32>, na::Vector2<f32>) {
+    let margin = if extended_canvas {
+        EXTENDED_CANVAS_MARGIN
+    } else {
+        0.0
     };
 
-    matrix![
-        l(p00), l(p01), 0.0, 0.0;
-        l(p10), l(p11), 0.0, 0.0;
-        0.0, 0.0, 0.0, 0.0;
-        0.0, 0.0, 0.0, 0.0;
-    ]
-    .transpose()
+    (
+        na::Vector2::repeat(-margin),
+        na::Vector2::repeat(1.0 + margin),
+    )
 }
 
-fn ferguson_patch_pt(
-    u: f32,
-    v: f32,
-    geom_x: &na::Matrix4<f32>,
-    geom_y: &na::Matrix4<f32>,
-) -> na::Vector2<f32> {
-    let u_vec = cubic_colvec(u);
-    let v_vec = cubic_colvec(v);
-
-    let x_acc = H.transpose() * geom_x.transpose() * H;
-    let y_acc = H.transpose() * geom_y.transpose() * H;
+/// Draws grid lines every `step` units of mesh space across the whole workspace.
+fn draw_grid(step: f32, view: &View) {
+    let min = view.pt_coord(&na::Vector2::zeros());
+    let max = view.pt_coord(&view.size());
 
-    let ux = x_acc * u_vec;
-    let uy = y_acc * u_vec;
+    let first_x = (min.x / step).ceil() as i32;
+    let last_x = (max.x / step).floor() as i32;
+    for i in first_x..=last_x {
+        let top = view.ws_coord(&na::Vector2::new(i as f32 * step, min.y));
+        let bottom = view.ws_coord(&na::Vector2::new(i as f32 * step, max.y));
+        draw_line(
+            top.x,
+            top.y,
+            bottom.x,
+            bottom.y,
+            1.0,
+            Color::new(0.0, 0.0, 0.0, 0.1),
+        );
+    }
 
-    na::Vector2::new(ux.dot(&v_vec), uy.dot(&v_vec))
+    let first_y = (min.y / step).ceil() as i32;
+    let last_y = (max.y / step).floor() as i32;
+    for i in first_y..=last_y {
+        let left = view.ws_coord(&na::Vector2::new(min.x, i as f32 * step));
+        let right = view.ws_coord(&na::Vector2::new(max.x, i as f32 * step));
+        draw_line(
+            left.x,
+            left.y,
+            right.x,
+            right.y,
+            1.0,
+            Color::new(0.0, 0.0, 0.0, 0.1),
+        );
+    }
 }
 
-fn ferguson_patch_col(
-    u: f32,
-    v: f32,
-    rgb_coeffs: (&na::Matrix4<f32>, &na::Matrix4<f32>, &na::Matrix4<f32>),
-) -> na::Vector3<f32> {
-    let u_vec = cubic_colvec(u);
-    let v_vec = cubic_colvec(v);
-
-    let r_acc = H.transpose() * rgb_coeffs.0.transpose() * H;
-    let g_acc = H.transpose() * rgb_coeffs.1.transpose() * H;
-    let b_acc = H.transpose() * rgb_coeffs.2.transpose() * H;
+/// Pivot for rotating and scaling the selection - its centroid or a point typed in by the user.
+fn selection_pivot(
+    mesh: &Mesh,
+    selection: &Selection,
+    at_centroid: bool,
+    pivot_texts: &[String; 2],
+) -> na::Vector2<f32> {
+    let centroid = selection.centroid(mesh).unwrap_or_else(na::Vector2::zeros);
 
-    let ur = r_acc * u_vec;
-    let ug = g_acc * u_vec;
-    let ub = b_acc * u_vec;
+    if at_centroid {
+        return centroid;
+    }
 
-    na::Vector3::new(ur.dot(&v_vec), ug.dot(&v_vec), ub.dot(&v_vec))
+    match (pivot_texts[0].parse::<f32>(), pivot_texts[1].parse::<f32>()) {
+        (Ok(x), Ok(y)) => na::Vector2::new(x, y),
+        _ => centroid,
+    }
 }
 
-fn draw_across_ferguson_axis(
-    geom_x: &na::Matrix4<f32>,
-    geom_y: &na::Matrix4<f32>,
-    rgb_coeffs: (&na::Matrix4<f32>, &na::Matrix4<f32>, &na::Matrix4<f32>),
-    const_val: f32,
-    steps: u32,
-    axis: Axis,
-) {
-    let u = |t: f32| match axis {
-        Axis::X => t,
-        Axis::Y => const_val,
-    };
+fn draw_across_ferguson_axis(patch: &Patch, const_val: f32, steps: u32, axis: Axis, view: &View) {
+    let params: Vec<f32> = (0..=steps).map(|i| i as f32 / steps as f32).collect();
 
-    let v = |t| match axis {
-        Axis::X => const_val,
-        Axis::Y => t,
+    let (points, colors) = match axis {
+        Axis::X => patch.evaluate_grid(&params, &[const_val]),
+        Axis::Y => patch.evaluate_grid(&[const_val], &params),
     };
 
-    let mut last_point = ferguson_patch_pt(u(0.0), v(0.0), geom_x, geom_y);
-
-    for i in 1..=steps {
-        let point = ferguson_patch_pt(
-            u(i as f32 / steps as f32),
-            v(i as f32 / steps as f32),
-            geom_x,
-            geom_y,
-        );
-
-        let color = ferguson_patch_col(
-            u(i as f32 / steps as f32),
-            v(i as f32 / steps as f32),
-            rgb_coeffs,
-        );
+    for (ends, color) in points.windows(2).zip(&colors[1..]) {
+        let (last_point, point) = (view.ws_coord(&ends[0]), view.ws_coord(&ends[1]));
 
         draw_line(
-            ws_coord(&last_point).x,
-            ws_coord(&last_point).y,
-            ws_coord(&point).x,
-            ws_coord(&point).y,
+            last_point.x,
+            last_point.y,
+            point.x,
+            point.y,
             2.0,
             Color::from_rgba(
-                (&color.x * 255.0) as u8,
-                (&color.y * 255.0) as u8,
-                (&color.z * 255.0) as u8,
+                (color.x * 255.0) as u8,
+                (color.y * 255.0) as u8,
+                (color.z * 255.0) as u8,
                 255,
             ),
         );
-        last_point = point;
     }
 }
 
-fn draw_hermite_from_geom(
-    geom_x: &na::Matrix4<f32>,
-    geom_y: &na::Matrix4<f32>,
-    rgb_coeffs: (&na::Matrix4<f32>, &na::Matrix4<f32>, &na::Matrix4<f32>),
-    steps: u32,
-) {
+fn draw_hermite_from_geom(patch: &Patch, steps: u32, view: &View) {
     // top
-    draw_across_ferguson_axis(geom_x, geom_y, rgb_coeffs, 0.0, steps, Axis::Y);
+    draw_across_ferguson_axis(patch, 0.0, steps, Axis::Y, view);
     // bottom
-    draw_across_ferguson_axis(geom_x, geom_y, rgb_coeffs, 1.0, steps, Axis::Y);
+    draw_across_ferguson_axis(patch, 1.0, steps, Axis::Y, view);
     // leading
-    draw_across_ferguson_axis(geom_x, geom_y, rgb_coeffs, 0.0, steps, Axis::X);
+    draw_across_ferguson_axis(patch, 0.0, steps, Axis::X, view);
     // trailing
-    draw_across_ferguson_axis(geom_x, geom_y, rgb_coeffs, 1.0, steps, Axis::X);
+    draw_across_ferguson_axis(patch, 1.0, steps, Axis::X, view);
+}
+
+/// Inverse of the mesh space to NDC mapping done by `construct_mesh`.
+fn ndc_to_pt(point: &na::Vector3<f32>) -> na::Vector2<f32> {
+    na::Vector2::new((point.x + 1.0) / 2.0, (1.0 - point.y) / 2.0)
+}
+
+/// Draws `construct_mesh` output as filled, vertex colored triangles, so the preview matches the export.
+fn draw_filled_mesh(preview: &Preview, view: &View) {
+    let (positions, colors, indexes) = preview.buffers();
+    let subdivs = preview.subdivs();
+
+    // Every patch gets its own block of vertices and indexes in `construct_mesh` output.
+    // Drawing patch by patch keeps indexes in u16 range and draw calls within macroquad limits.
+    let steps = subdivs + 1;
+    let patch_vertices = (steps + 1) * (steps + 1);
+    let patch_indexes = steps * steps * 6;
+
+    for (patch, indexes) in indexes.chunks(patch_indexes).enumerate() {
+        let base = patch * patch_vertices;
+
+        let vertices = (base..base + patch_vertices)
+            .map(|idx| {
+                let point = view.ws_coord(&ndc_to_pt(&positions[idx]));
+                let color = colors[idx];
+
+                Vertex {
+                    position: vec3(point.x, point.y, 0.0),
+                    uv: Vec2::ZERO,
+                    color: Color::new(color.x, color.y, color.z, 1.0),
+                }
+            })
+            .collect();
+
+        let indices = indexes
+            .iter()
+            .map(|&idx| (idx as usize - base) as u16)
+            .collect();
+
+        draw_mesh(&QuadMesh {
+            vertices,
+            indices,
+            texture: None,
+        });
+    }
+}
 
-    for i in 0..20 {
-        for j in 0..20 {
-            let u = i as f32 / 20.0;
-            let v = j as f32 / 20.0;
+/// Red outline around a patch which folds over or intersects itself.
+fn draw_fold_highlight(mesh: &Mesh, w: usize, h: usize, view: &View) {
+    let patch = mesh.patch(w, h);
 
-            let point = ferguson_patch_pt(u, v, geom_x, geom_y);
-            let color = ferguson_patch_col(u, v, rgb_coeffs);
+    let edges: [fn(f32) -> (f32, f32); 4] =
+        [|t| (t, 0.0), |t| (t, 1.0), |t| (0.0, t), |t| (1.0, t)];
 
-            draw_circle(
-                ws_coord(&point).x,
-                ws_coord(&point).y,
-                2.0,
-                Color::from_rgba(
-                    (&color.x * 255.0) as u8,
-                    (&color.y * 255.0) as u8,
-                    (&color.z * 255.0) as u8,
-                    255,
-                ),
-            );
+    for edge in edges {
+        let at = |i: u32| {
+            let (u, v) = edge(i as f32 / 50.0);
+            view.ws_coord(&patch.point(u, v))
+        };
+
+        for i in 0..50 {
+            let (start, end) = (at(i), at(i + 1));
+            draw_line(start.x, start.y, end.x, end.y, 3.0, RED);
         }
     }
 }
 
-fn construct_mesh(
-    mesh: &Mesh,
-    subdivs: usize,
-) -> (Vec<na::Vector3<f32>>, Vec<na::Vector3<f32>>, Vec<u32>) {
-    let col_len = (mesh.width - 1) * (subdivs + 2);
-    let row_len = (mesh.height - 1) * (subdivs + 2);
-
-    let entries = col_len * row_len;
-
-    let mut positions = Vec::with_capacity(entries);
-    let mut colors = Vec::with_capacity(entries);
-    let mut indexes = Vec::with_capacity(entries * 3 * 2);
-
-    // mesh with subdivs = 3
-    //  0  1  2  3  4
-    //  5  6  7  8  9
-    // 10 11 12 13 14
-
-    // indexes:
-    // 5 1 0
-    // 5 6 1
-    // 7 2 1
-    // 6 7 2
-    // 7 3 2
-    // 7 8 3
-    // 8 4 3
-    // 8 9 4
-    // 10 6 5
-    // 10 11 6
-    // 11 7 6
-    // 11 12 7
-    // 12 8 7
-    // 12 13 8
-    // 13 9 8
-    // 13 14 9
-
-    for w in 0..mesh.width - 1 {
-        for h in 0..mesh.height - 1 {
-            let p00 = mesh.point_at(w, h);
-            let p01 = mesh.point_at(w, h + 1);
-            let p10 = mesh.point_at(w + 1, h);
-            let p11 = mesh.point_at(w + 1, h + 1);
-
-            let x_coeff = geometric_coefficients(p00, p01, p10, p11, Axis::X);
-            let y_coeff = geometric_coefficients(p00, p01, p10, p11, Axis::Y);
-            let r_coeff = color_coefficients(p00, p01, p10, p11, ColorAxis::R);
-            let g_coeff = color_coefficients(p00, p01, p10, p11, ColorAxis::G);
-            let b_coeff = color_coefficients(p00, p01, p10, p11, ColorAxis::B);
-
-            let steps = subdivs + 1;
-            let index_start = positions.len();
-
-            for i in 0..=steps {
-                for j in 0..=steps {
-                    let u = i as f32 / steps as f32;
-                    let v = j as f32 / steps as f32;
-
-                    let point = {
-                        let mut p = ferguson_patch_pt(u, v, &x_coeff, &y_coeff);
-                        p *= 2.0;
-                        p -= na::Vector2::new(1.0, 1.0);
-                        p.component_mul_assign(&na::Vector2::new(1.0, -1.0));
-
-                        na::Vector3::new(p.x, p.y, 0.0)
-                    };
+/// A full seed fixes the size and harmony, so changing either keeps only its text.
+fn detach_seed(seed_text: &mut String) {
+    if let Some(seed) = Seed::parse(seed_text) {
+        *seed_text = seed.text;
+    }
+}
+
+/// Loads a reference image together with a texture for drawing it as an underlay.
+fn load_reference(path: &str) -> Result<(ReferenceImage, Texture2D), String> {
+    let image = ReferenceImage::load(path).map_err(|err| format!("{path}: {err}"))?;
+
+    let bytes: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|color| {
+            [
+                (color.x * 255.0) as u8,
+                (color.y * 255.0) as u8,
+                (color.z * 255.0) as u8,
+                255,
+            ]
+        })
+        .collect();
+    let texture = Texture2D::from_rgba8(image.width as u16, image.height as u16, &bytes);
 
-                    let color = ferguson_patch_col(u, v, (&r_coeff, &g_coeff, &b_coeff));
+    Ok((image, texture))
+}
 
-                    positions.push(point);
-                    colors.push(color);
+/// Draws the reference image at its own aspect ratio, see `Underlay::image_rect`.
+fn draw_underlay(
+    image: &ReferenceImage,
+    texture: &Texture2D,
+    underlay: &Underlay,
+    aspect: f32,
+    view: &View,
+) {
+    let (origin, size) = underlay.image_rect(image, aspect);
+    let (origin, size) = (view.ws_coord(&origin), size.component_mul(&view.scale()));
+
+    draw_texture_ex(
+        texture,
+        origin.x,
+        origin.y,
+        Color::new(1.0, 1.0, 1.0, underlay.opacity),
+        DrawTextureParams {
+            dest_size: Some(vec2(size.x, size.y)),
+            ..Default::default()
+        },
+    );
+}
+
+/// Straight lines between neighbouring control points, plus the handles of Bezier patches.
+fn draw_control_net(mesh: &Mesh, view: &View) {
+    for h in 0..mesh.height {
+        for w in 0..mesh.width {
+            let control_point = mesh.point_at(w, h);
+            let point = view.ws_coord(&control_point.position);
+
+            if mesh.kind == PatchKind::Bezier {
+                let handles = [
+                    (w + 1 < mesh.width, control_point.u_tangent),
+                    (w > 0, -control_point.u_tangent),
+                    (h + 1 < mesh.height, control_point.v_tangent),
+                    (h > 0, -control_point.v_tangent),
+                ];
+
+                for (_, offset) in handles.iter().filter(|(inside, _)| *inside) {
+                    let handle = view.ws_coord(&(control_point.position + offset));
+                    draw_line(point.x, point.y, handle.x, handle.y, 1.0, DARKGRAY);
+                    draw_circle(handle.x, handle.y, 2.5, DARKGRAY);
                 }
             }
 
-            let row_len = steps + 1;
-
-            for r in 0..steps {
-                for c in 0..steps {
-                    indexes.push((index_start + r * row_len + c + row_len) as u32);
-                    indexes.push((index_start + r * row_len + c + 1) as u32);
-                    indexes.push((index_start + r * row_len + c) as u32);
+            if w + 1 < mesh.width {
+                let right = view.ws_coord(&mesh.point_at(w + 1, h).position);
+                draw_line(point.x, point.y, right.x, right.y, 1.0, GRAY);
+            }
 
-                    indexes.push((index_start + r * row_len + c + row_len) as u32);
-                    indexes.push((index_start + r * row_len + c + row_len + 1) as u32);
-                    indexes.push((index_start + r * row_len + c + 1) as u32);
-                }
+            if h + 1 < mesh.height {
+                let below = view.ws_coord(&mesh.point_at(w, h + 1).position);
+                draw_line(point.x, point.y, below.x, below.y, 1.0, GRAY);
             }
         }
     }
-
-    (positions, colors, indexes)
 }
 
 #[macroquad::main("Mesh Gradient")]
 async fn main() {
     #[rustfmt::skip]
-    let mut mesh = Mesh::new(
-        3,
-        3,
-        vec![
-            vector![0.0, 0.0, 0.0], vector![0.0, 0.0, 0.0], vector![0.0, 0.0, 0.0],
-            vector![0.0, 0.0, 1.0], vector![0.0, 0.0, 1.0], vector![0.0, 0.0, 1.0],
-            vector![0.0, 1.0, 0.0], vector![0.0, 1.0, 0.0], vector![0.0, 1.0, 0.0]
-        ],
-    );
+    let Document { mut mesh, mut underlay, mut aspect } = match std::env::args().nth(1) {
+        Some(fname) => Document::load(&fname).unwrap_or_else(|err| panic!("{fname}: {err}")),
+        None => Document {
+            mesh: Mesh::new(
+                3,
+                3,
+                vec![
+                    vector![0.0, 0.0, 0.0], vector![0.0, 0.0, 0.0], vector![0.0, 0.0, 0.0],
+                    vector![0.0, 0.0, 1.0], vector![0.0, 0.0, 1.0], vector![0.0, 0.0, 1.0],
+                    vector![0.0, 1.0, 0.0], vector![0.0, 1.0, 0.0], vector![0.0, 1.0, 0.0]
+                ],
+            ),
+            underlay: None,
+            aspect: 1.0,
+        },
+    };
 
     set_window_size((WORKSPACE_SIZE_W + UI_SIZE) as u32, WORKSPACE_SIZE_H as u32);
 
-    let mut active_point_idx: Option<usize> = None;
-    let mut last_mouse_pos: Option<(f32, f32)> = None;
+    let mut view = View::new(WORKSPACE_SIZE_W, WORKSPACE_SIZE_H);
+    // Last mouse position while panning the view.
+    let mut pan_anchor: Option<na::Vector2<f32>> = None;
+    let ui_window = hash!();
+
+    let mut selection = Selection::default();
+    let mut drag: Option<Drag> = None;
+    // Screen position where a rubber-band selection started.
+    let mut rubber_band: Option<na::Vector2<f32>> = None;
+    // Whether keys go to the workspace rather than the UI - whichever was clicked last, so typing
+    // into an editbox doesn't move points.
+    let mut workspace_focused = true;
+    let mut extended_canvas = false;
+    let mut snapping = Snapping::default();
+    let mut show_fill = true;
+    let mut show_outline = true;
+    let mut show_control_net = true;
 
     let mut x_pos_text = String::new();
     let mut y_pos_text = String::new();
-    let mut subdivs = 0.0;
+    let mut tangent_texts: [String; 4] = Default::default();
+    let mut twist_texts: [String; 2] = Default::default();
+    let mut angle_text = String::from("0");
+    let mut scale_texts = [String::from("1"), String::from("1")];
+    let mut pivot_at_centroid = true;
+    let mut pivot_texts: [String; 2] = Default::default();
+    let mut subdivs: f32 = 0.0;
+    let mut export_attributes = false;
+    let mut baking: Option<Baking> = None;
+    let mut bake_message: Option<String> = None;
+    let mut grid_w = mesh.width as f32;
+    let mut grid_h = mesh.height as f32;
+
+    let mut history = History::default();
+
+    let builtin_presets = preset::builtin();
+    let mut user_presets = preset::user();
+    let mut preset_name = String::new();
+    let mut seed_text = String::new();
+    let mut harmony = Harmony::Analogous;
+
+    // Loaded image of the `underlay`. An underlay whose image failed to load is kept without one,
+    // so saving the document doesn't lose its placement.
+    let mut reference: Option<(ReferenceImage, Texture2D)> = None;
+    let mut image_path = String::new();
+    let mut image_error: Option<String> = None;
+    let mut blur_radius: f32 = 0.0;
+    // While set, colors are resampled from the image whenever points move.
+    let mut follow_image = false;
+    // Positions, blur radius, aspect and image placement the colors were last sampled with.
+    let mut sampled_with: Option<(Vec<na::Vector2<f32>>, f32, f32, Underlay)> = None;
+
+    if let Some(saved) = &underlay {
+        image_path.clone_from(&saved.path);
+
+        match load_reference(&saved.path) {
+            Ok(loaded) => reference = Some(loaded),
+            Err(err) => image_error = Some(err),
+        }
+    }
+
+    let mut preview = Preview::new(&mesh, subdivs.floor() as usize);
 
     loop {
+        view.resize(screen_width() - UI_SIZE, screen_height());
+        view.set_aspect(aspect);
+
+        // Set when undo or redo brought back points together with the colors they were sampled with.
+        let mut restored = false;
+
+        if baking.as_ref().is_some_and(Baking::is_finished) {
+            bake_message = baking.take().map(Baking::finish);
+        }
+
         clear_background(WHITE);
 
+        preview.update(&mesh, subdivs.floor() as usize);
+
+        if show_fill {
+            draw_filled_mesh(&preview, &view);
+        }
+
+        if let (Some((image, texture)), Some(underlay)) = (&reference, &underlay) {
+            draw_underlay(image, texture, underlay, aspect, &view);
+        }
+
+        let frame_origin = view.ws_coord(&na::Vector2::new(0.0, 0.0));
+        let frame_end = view.ws_coord(&na::Vector2::new(1.0, 1.0));
+        draw_rectangle_lines(
+            frame_origin.x,
+            frame_origin.y,
+            frame_end.x - frame_origin.x,
+            frame_end.y - frame_origin.y,
+            1.0,
+            LIGHTGRAY,
+        );
+
+        if snapping.show_grid {
+            draw_grid(snapping.grid_step, &view);
+        }
+
+        if show_outline {
+            for w in 0..mesh.width - 1 {
+                for h in 0..mesh.height - 1 {
+                    draw_hermite_from_geom(&mesh.patch(w, h), 100, &view);
+                }
+            }
+        }
+
+        let folds = preview.folds();
+
+        for &(w, h, _) in folds {
+            draw_fold_highlight(&mesh, w, h, &view);
+        }
+
+        if show_control_net {
+            draw_control_net(&mesh, &view);
+        }
+
         for (idx, point) in mesh.points.iter().enumerate() {
-            let spoint = ws_coord(&point.position);
+            let spoint = view.ws_coord(&point.position);
 
+            // Points stay visible without the control net, so they can still be picked.
             draw_circle_lines(
                 spoint.x,
                 spoint.y,
@@ -449,113 +470,741 @@ async fn main() {
                 ),
             );
 
-            if let Some(active_point_idx) = active_point_idx {
-                if active_point_idx == idx {
-                    draw_circle_lines(spoint.x, spoint.y, 7.0, 2.0, RED);
-                }
+            if selection.contains(idx) {
+                draw_circle_lines(spoint.x, spoint.y, 7.0, 2.0, RED);
             }
         }
 
+        if let Some(start) = rubber_band {
+            let (x, y) = mouse_position();
+            draw_rectangle_lines(
+                start.x.min(x),
+                start.y.min(y),
+                (x - start.x).abs(),
+                (y - start.y).abs(),
+                1.0,
+                DARKGRAY,
+            );
+        }
+
+        // The panel sticks to the right edge of the window when it gets resized.
+        root_ui().move_window(ui_window, vec2(view.size().x, 0.0));
         root_ui().window(
-            hash!(),
-            vec2(WORKSPACE_SIZE_W, 0.0),
-            vec2(UI_SIZE, WORKSPACE_SIZE_H),
+            ui_window,
+            vec2(view.size().x, 0.0),
+            vec2(UI_SIZE, view.size().y),
             |ui| {
-                if let Some(point_idx) = active_point_idx {
+                if let Some(point_idx) = selection.single() {
                     let point = &mesh.points[point_idx];
 
                     ui.label(None, &format!("x: {}", point.position.x));
                     ui.label(None, &format!("y: {}", point.position.y));
+                    ui.label(None, &format!("{}", point.constraint));
 
                     ui.editbox(hash!(), vec2(100.0, 20.0), &mut x_pos_text);
                     ui.editbox(hash!(), vec2(100.0, 20.0), &mut y_pos_text);
 
                     if ui.button(None, "Update point") {
-                        let x = x_pos_text.parse::<f32>();
-                        let y = y_pos_text.parse::<f32>();
+                        let from = mesh.points[point_idx].position;
+                        let mut to = from;
+
+                        if let Ok(x) = x_pos_text.parse::<f32>() {
+                            to.x = x;
+                        }
 
-                        if let Ok(x) = x {
-                            mesh.points[point_idx].position.x = x;
+                        if let Ok(y) = y_pos_text.parse::<f32>() {
+                            to.y = y;
                         }
 
-                        if let Ok(y) = y {
-                            mesh.points[point_idx].position.y = y;
+                        // Unparsable text leaves the value as it was, which isn't worth an undo entry.
+                        if to != from {
+                            history.apply(
+                                &mut mesh,
+                                Edit::MovePoint {
+                                    idx: point_idx,
+                                    from,
+                                    to,
+                                },
+                            );
                         }
                     }
-                } else {
+
+                    ui.separator();
+                    let from = mesh.points[point_idx].color;
+                    let mut to = from;
+                    ui.slider(hash!(), "R", 0.0..1.0, &mut to.x);
+                    ui.slider(hash!(), "G", 0.0..1.0, &mut to.y);
+                    ui.slider(hash!(), "B", 0.0..1.0, &mut to.z);
+
+                    if to != from {
+                        history.apply_merged(
+                            &mut mesh,
+                            Edit::SetColor {
+                                idx: point_idx,
+                                from,
+                                to,
+                            },
+                        );
+                    }
+
+                    ui.separator();
+                    let point = &mesh.points[point_idx];
+                    ui.label(
+                        None,
+                        &format!("u: ({:.3}, {:.3})", point.u_tangent.x, point.u_tangent.y),
+                    );
+                    ui.editbox(hash!(), vec2(45.0, 20.0), &mut tangent_texts[0]);
+                    ui.same_line(55.0);
+                    ui.editbox(hash!(), vec2(45.0, 20.0), &mut tangent_texts[1]);
+                    ui.label(
+                        None,
+                        &format!("v: ({:.3}, {:.3})", point.v_tangent.x, point.v_tangent.y),
+                    );
+                    ui.editbox(hash!(), vec2(45.0, 20.0), &mut tangent_texts[2]);
+                    ui.same_line(55.0);
+                    ui.editbox(hash!(), vec2(45.0, 20.0), &mut tangent_texts[3]);
+
+                    if ui.button(None, "Update tangents") {
+                        let from = (point.u_tangent, point.v_tangent);
+                        let mut to = from;
+
+                        let parsed = tangent_texts.each_ref().map(|text| text.parse::<f32>());
+
+                        if let Ok(x) = parsed[0] {
+                            to.0.x = x;
+                        }
+
+                        if let Ok(y) = parsed[1] {
+                            to.0.y = y;
+                        }
+
+                        if let Ok(x) = parsed[2] {
+                            to.1.x = x;
+                        }
+
+                        if let Ok(y) = parsed[3] {
+                            to.1.y = y;
+                        }
+
+                        if to != from {
+                            history.apply(
+                                &mut mesh,
+                                Edit::SetTangents {
+                                    idx: point_idx,
+                                    from,
+                                    to,
+                                },
+                            );
+                        }
+                    }
+
+                    let point = &mesh.points[point_idx];
+                    ui.label(
+                        None,
+                        &format!("twist: ({:.3}, {:.3})", point.twist.x, point.twist.y),
+                    );
+                    ui.editbox(hash!(), vec2(45.0, 20.0), &mut twist_texts[0]);
+                    ui.same_line(55.0);
+                    ui.editbox(hash!(), vec2(45.0, 20.0), &mut twist_texts[1]);
+
+                    if ui.button(None, "Update twist") {
+                        let from = point.twist;
+                        let mut to = from;
+
+                        if let Ok(x) = twist_texts[0].parse::<f32>() {
+                            to.x = x;
+                        }
+
+                        if let Ok(y) = twist_texts[1].parse::<f32>() {
+                            to.y = y;
+                        }
+
+                        if to != from {
+                            history.apply(
+                                &mut mesh,
+                                Edit::SetTwist {
+                                    idx: point_idx,
+                                    from,
+                                    to,
+                                },
+                            );
+                        }
+                    }
+                } else if selection.is_empty() {
                     ui.label(None, "No point selected");
+                } else {
+                    ui.label(None, &format!("{} points selected", selection.len()));
                 }
 
-                ui.separator();
-                ui.slider(hash!(), "Subdivs", 0.0..20.0, &mut subdivs);
-                ui.label(None, &format!("Subdivs: {}", subdivs.floor()));
-                if ui.button(None, "Save mesh") {
-                    let (positions, colors, indexes) =
-                        construct_mesh(&mesh, subdivs.floor() as usize);
+                if !selection.is_empty() {
+                    ui.separator();
+                    ui.label(None, "Angle (deg)");
+                    ui.editbox(hash!(), vec2(45.0, 20.0), &mut angle_text);
+                    ui.same_line(55.0);
+                    if ui.button(None, "Rotate") {
+                        if let Ok(angle) = angle_text.parse::<f32>() {
+                            let m = na::Rotation2::new(angle.to_radians()).into_inner();
+                            let pivot =
+                                selection_pivot(&mesh, &selection, pivot_at_centroid, &pivot_texts);
+
+                            let edit =
+                                selection.transform_edit(&mesh, &m, pivot, na::Vector2::zeros());
+                            history.apply(&mut mesh, edit);
+                        }
+                    }
 
-                    let json = serde_json::json!(
+                    ui.label(None, "Scale (x, y)");
+                    ui.editbox(hash!(), vec2(45.0, 20.0), &mut scale_texts[0]);
+                    ui.same_line(55.0);
+                    ui.editbox(hash!(), vec2(45.0, 20.0), &mut scale_texts[1]);
+                    ui.same_line(110.0);
+                    if ui.button(None, "Scale") {
+                        if let (Ok(x), Ok(y)) =
+                            (scale_texts[0].parse::<f32>(), scale_texts[1].parse::<f32>())
                         {
-                            "positions": positions,
-                            "colors": colors,
-                            "indexes": indexes
+                            let m = na::Matrix2::new(x, 0.0, 0.0, y);
+                            let pivot =
+                                selection_pivot(&mesh, &selection, pivot_at_centroid, &pivot_texts);
+
+                            let edit =
+                                selection.transform_edit(&mesh, &m, pivot, na::Vector2::zeros());
+                            history.apply(&mut mesh, edit);
+                        }
+                    }
+
+                    ui.checkbox(hash!(), "Pivot at centroid", &mut pivot_at_centroid);
+                    if !pivot_at_centroid {
+                        ui.editbox(hash!(), vec2(45.0, 20.0), &mut pivot_texts[0]);
+                        ui.same_line(55.0);
+                        ui.editbox(hash!(), vec2(45.0, 20.0), &mut pivot_texts[1]);
+                    }
+
+                    if ui.button(None, "Select row") {
+                        selection.grow_to_rows(&mesh);
+                    }
+                    ui.same_line(0.0);
+                    if ui.button(None, "Select column") {
+                        selection.grow_to_columns(&mesh);
+                    }
+                }
+
+                ui.separator();
+                ui.checkbox(hash!(), "Fill", &mut show_fill);
+                ui.checkbox(hash!(), "Patch outline", &mut show_outline);
+                ui.checkbox(hash!(), "Control net", &mut show_control_net);
+                if ui.button(None, "Reset view") {
+                    view.reset();
+                }
+
+                ui.separator();
+                ui.checkbox(hash!(), "Show grid", &mut snapping.show_grid);
+                ui.checkbox(hash!(), "Snap to grid", &mut snapping.to_grid);
+                ui.checkbox(hash!(), "Snap to points", &mut snapping.to_axes);
+                ui.checkbox(hash!(), "Snap to edges", &mut snapping.to_edges);
+                ui.slider(hash!(), "Step", 0.01..0.5, &mut snapping.grid_step);
+
+                ui.separator();
+                let mut lock_boundary = mesh.is_boundary_locked();
+                ui.checkbox(hash!(), "Lock boundary", &mut lock_boundary);
+                if lock_boundary != mesh.is_boundary_locked() {
+                    let from = Box::new(mesh.clone());
+                    let mut to = from.clone();
+
+                    if lock_boundary {
+                        to.lock_boundary();
+                    } else {
+                        to.unlock_boundary();
+                    }
+
+                    history.apply(&mut mesh, Edit::ReplaceMesh { from, to });
+                }
+
+                if ui.button(None, format!("Patches: {}", mesh.kind).as_str()) {
+                    let kind = match mesh.kind {
+                        PatchKind::Hermite => PatchKind::Bezier,
+                        PatchKind::Bezier => PatchKind::CatmullRom,
+                        PatchKind::CatmullRom => PatchKind::BSpline,
+                        PatchKind::BSpline => PatchKind::Hermite,
+                    };
+
+                    let from = Box::new(mesh.clone());
+                    let to = Box::new(mesh.with_kind(kind));
+
+                    history.apply(&mut mesh, Edit::ReplaceMesh { from, to });
+                }
+
+                if ui.button(None, "Auto twists") {
+                    let from = Box::new(mesh.clone());
+                    let mut to = from.clone();
+                    to.estimate_twists();
+
+                    history.apply(&mut mesh, Edit::ReplaceMesh { from, to });
+                }
+                ui.same_line(0.0);
+                if ui.button(None, "Zero twists") {
+                    let from = Box::new(mesh.clone());
+                    let mut to = from.clone();
+                    to.clear_twists();
+
+                    history.apply(&mut mesh, Edit::ReplaceMesh { from, to });
+                }
+
+                ui.checkbox(hash!(), "Extended canvas", &mut extended_canvas);
+                ui.slider(hash!(), "Aspect", 0.25..4.0, &mut aspect);
+                let grid = (grid_w.floor(), grid_h.floor());
+                ui.slider(hash!(), "Grid W", 2.0..10.0, &mut grid_w);
+                ui.slider(hash!(), "Grid H", 2.0..10.0, &mut grid_h);
+                if (grid_w.floor(), grid_h.floor()) != grid {
+                    detach_seed(&mut seed_text);
+                }
+                if ui.button(
+                    None,
+                    format!("New {}x{} grid", grid_w.floor(), grid_h.floor()).as_str(),
+                ) {
+                    let from = Box::new(mesh.clone());
+                    let to =
+                        Box::new(mesh.resized(grid_w.floor() as usize, grid_h.floor() as usize));
+
+                    history.apply(&mut mesh, Edit::ReplaceMesh { from, to });
+                    selection.clear();
+                }
+
+                ui.separator();
+                ui.label(None, "Reference image");
+                ui.editbox(hash!(), vec2(100.0, 20.0), &mut image_path);
+                ui.same_line(110.0);
+                if ui.button(None, "Load image") {
+                    match load_reference(&image_path) {
+                        Ok(loaded) => {
+                            reference = Some(loaded);
+                            // Reloading the image of the underlay keeps where it was placed.
+                            underlay = match underlay.take() {
+                                Some(kept) if kept.path == image_path => Some(kept),
+                                _ => Some(Underlay::new(image_path.clone())),
+                            };
+                            image_error = None;
                         }
+                        Err(err) => image_error = Some(err),
+                    }
+                }
+                if let Some(err) = &image_error {
+                    ui.label(None, err);
+                }
+                if let (Some((image, _)), Some(underlay)) = (&reference, &mut underlay) {
+                    ui.slider(hash!(), "Opacity", 0.0..1.0, &mut underlay.opacity);
+                    ui.slider(hash!(), "Scale", 0.1..3.0, &mut underlay.scale);
+                    ui.slider(hash!(), "Offset X", -1.0..1.0, &mut underlay.offset.x);
+                    ui.slider(hash!(), "Offset Y", -1.0..1.0, &mut underlay.offset.y);
+
+                    ui.slider(hash!(), "Blur", 0.0..20.0, &mut blur_radius);
+                    if ui.button(None, "Sample colors") {
+                        let colors = image.sample_colors(&mesh, underlay, aspect, blur_radius);
+                        let from = mesh.points.clone();
+                        let to = from
+                            .iter()
+                            .zip(colors)
+                            .map(|(point, color)| {
+                                let mut point = point.clone();
+                                point.color = color;
+                                point
+                            })
+                            .collect();
+
+                        let edit = Edit::SetPoints {
+                            idxs: (0..mesh.points.len()).collect(),
+                            from,
+                            to,
+                        };
+                        history.apply(&mut mesh, edit);
+                    }
+                    ui.checkbox(hash!(), "Follow points", &mut follow_image);
+                }
+
+                ui.separator();
+                ui.label(None, "Presets");
+                let mut picked: Option<&Preset> = None;
+                for (i, preset) in builtin_presets.iter().chain(&user_presets).enumerate() {
+                    if i % 2 != 0 {
+                        ui.same_line(0.0);
+                    }
+                    if ui.button(None, preset.name.as_str()) {
+                        picked = Some(preset);
+                    }
+                }
+                if let Some(preset) = picked {
+                    let from = Box::new(mesh.clone());
+                    let to = Box::new(preset.mesh.clone());
+
+                    history.apply(&mut mesh, Edit::ReplaceMesh { from, to });
+                    selection.clear();
+                    grid_w = mesh.width as f32;
+                    grid_h = mesh.height as f32;
+                    detach_seed(&mut seed_text);
+                }
+
+                ui.label(None, "Seed");
+                ui.editbox(hash!(), vec2(100.0, 20.0), &mut seed_text);
+                ui.same_line(110.0);
+                if ui.button(None, "New seed") {
+                    seed_text = format!(
+                        "{:x}",
+                        SystemTime::now()
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .unwrap()
+                            .as_nanos()
                     );
+                }
+                if ui.button(None, format!("Harmony: {harmony}").as_str()) {
+                    harmony = match harmony {
+                        Harmony::Analogous => Harmony::Complementary,
+                        Harmony::Complementary => Harmony::Triadic,
+                        Harmony::Triadic => Harmony::Palette,
+                        Harmony::Palette => Harmony::Analogous,
+                    };
+                    detach_seed(&mut seed_text);
+                }
+                // A full seed brings its own size and harmony, plain text takes the current ones.
+                let seed = Seed::parse(&seed_text).unwrap_or_else(|| Seed {
+                    width: grid_w.floor() as usize,
+                    height: grid_h.floor() as usize,
+                    harmony,
+                    text: seed_text.clone(),
+                });
+                if ui.button(
+                    None,
+                    format!("Randomize {}x{}", seed.width, seed.height).as_str(),
+                ) {
+                    let from = Box::new(mesh.clone());
+                    let mut to = Box::new(seed.mesh());
+
+                    grid_w = seed.width as f32;
+                    grid_h = seed.height as f32;
+                    harmony = seed.harmony;
+                    // The full seed, ready to be copied and shared.
+                    seed_text = seed.to_string();
+
+                    if mesh.is_boundary_locked() {
+                        to.lock_boundary();
+                    }
+
+                    history.apply(&mut mesh, Edit::ReplaceMesh { from, to });
+                    selection.clear();
+                }
+
+                ui.editbox(hash!(), vec2(100.0, 20.0), &mut preset_name);
+                ui.same_line(110.0);
+                if ui.button(None, "Save preset") {
+                    bake_message = Some(match preset::save_user(&preset_name, &mesh) {
+                        Ok(path) => {
+                            user_presets = preset::user();
+                            format!("Saved {}", path.display())
+                        }
+                        Err(err) => format!("Saving preset failed: {err}"),
+                    });
+                }
 
-                    serde_json::to_writer(
-                        std::fs::File::create(format!(
-                            "mesh-{}-subdiv{}.json",
-                            SystemTime::now()
-                                .duration_since(SystemTime::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs(),
-                            subdivs.floor() as usize
-                        ))
-                        .unwrap(),
-                        &json,
-                    )
-                    .unwrap();
+                ui.separator();
+                if ui.button(None, "Undo") {
+                    restored |= history.undo(&mut mesh);
+                }
+                ui.same_line(0.0);
+                if ui.button(None, "Redo") {
+                    restored |= history.redo(&mut mesh);
+                }
+
+                ui.separator();
+                ui.slider(hash!(), "Subdivs", 0.0..MAX_SUBDIVS as f32, &mut subdivs);
+                ui.label(None, &format!("Subdivs: {}", subdivs.floor()));
+                if !folds.is_empty() {
+                    ui.label(None, &format!("{} patches fold over", folds.len()));
+                }
+                ui.checkbox(hash!(), "Export attributes", &mut export_attributes);
+                if let Some(baking) = &baking {
+                    ui.label(None, &format!("Baking {:.0}%", baking.progress() * 100.0));
+                } else if ui.button(None, "Save mesh") {
+                    let path = format!(
+                        "mesh-{}-subdiv{}.json",
+                        SystemTime::now()
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .unwrap()
+                            .as_secs(),
+                        subdivs.floor() as usize
+                    );
+
+                    let attributes = if export_attributes {
+                        Attributes::ALL
+                    } else {
+                        Attributes::default()
+                    };
+
+                    baking = Some(Baking::start(
+                        mesh.clone(),
+                        subdivs.floor() as usize,
+                        aspect,
+                        attributes,
+                        path.into(),
+                    ));
+                    bake_message = None;
+                }
+                ui.same_line(0.0);
+                if ui.button(None, "Save document") {
+                    let document = Document {
+                        mesh: mesh.clone(),
+                        underlay: underlay.clone(),
+                        aspect,
+                    };
+
+                    let path = format!(
+                        "gradient-{}.json",
+                        SystemTime::now()
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .unwrap()
+                            .as_secs()
+                    );
+
+                    bake_message = Some(match document.save(&path) {
+                        Ok(()) => format!("Saved {path}"),
+                        Err(err) => format!("Saving {path} failed: {err}"),
+                    });
+                }
+                ui.same_line(0.0);
+                if ui.button(None, "Save PDF") {
+                    let path = format!(
+                        "gradient-{}.pdf",
+                        SystemTime::now()
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .unwrap()
+                            .as_secs()
+                    );
+
+                    bake_message = Some(
+                        match std::fs::write(&path, pdf(&mesh, subdivs.floor() as usize)) {
+                            Ok(()) => format!("Saved {path}"),
+                            Err(err) => format!("Saving {path} failed: {err}"),
+                        },
+                    );
+                }
+
+                if let Some(message) = &bake_message {
+                    ui.label(None, message);
                 }
             },
         );
 
-        if is_mouse_button_down(MouseButton::Left) {
-            let mouse_pos = mouse_position();
+        let mouse_pos = na::Vector2::new(mouse_position().0, mouse_position().1);
+
+        let ctrl = is_key_down(KeyCode::LeftControl)
+            || is_key_down(KeyCode::RightControl)
+            || is_key_down(KeyCode::LeftSuper)
+            || is_key_down(KeyCode::RightSuper);
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            workspace_focused = view.contains(mouse_pos);
+        }
 
-            if mouse_pos.0 < WORKSPACE_SIZE_W {
-                if let Some(last_mouse_pos) = last_mouse_pos {
-                    let last_mouse_pos = na::Vector2::new(last_mouse_pos.0, last_mouse_pos.1);
-                    let mouse_pos = na::Vector2::new(mouse_pos.0, mouse_pos.1);
+        if view.contains(mouse_pos) {
+            let (_, wheel) = mouse_wheel();
 
-                    if let Some(active_point_idx) = active_point_idx {
-                        let delta = pt_coord(&(mouse_pos - last_mouse_pos));
-                        mesh.points[active_point_idx].position += delta;
+            if wheel != 0.0 {
+                view.zoom_at(mouse_pos, 1.1_f32.powf(wheel.signum()));
+            }
+        }
+
+        if (is_mouse_button_pressed(MouseButton::Right)
+            || is_mouse_button_pressed(MouseButton::Middle))
+            && view.contains(mouse_pos)
+        {
+            pan_anchor = Some(mouse_pos);
+        }
+
+        if let Some(anchor) = &mut pan_anchor {
+            if is_mouse_button_down(MouseButton::Right) || is_mouse_button_down(MouseButton::Middle)
+            {
+                view.pan_by(mouse_pos - *anchor);
+                *anchor = mouse_pos;
+            } else {
+                pan_anchor = None;
+            }
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) && view.contains(mouse_pos) {
+            match point_idx(mouse_pos, &mesh, &view) {
+                Some(idx) => {
+                    if shift {
+                        selection.toggle(idx);
+                    } else if !selection.contains(idx) {
+                        selection.select(idx);
                     }
+
+                    drag = selection.contains(idx).then(|| {
+                        Drag::new(idx, view.pt_coord(&mouse_pos), mesh.points[idx].position)
+                    });
                 }
+                None => {
+                    if !shift {
+                        selection.clear();
+                    }
 
-                active_point_idx = point_idx(na::Vector2::new(mouse_pos.0, mouse_pos.1), &mesh);
-                last_mouse_pos = Some(mouse_pos);
+                    rubber_band = Some(mouse_pos);
+                }
+            }
+        }
+
+        if is_mouse_button_down(MouseButton::Left) {
+            if let Some(drag) = &mut drag {
+                let precise = is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt);
+                let bounds = drag_bounds(extended_canvas);
+                let from = mesh.points[drag.idx].position;
+                let mut to = drag.target(view.pt_coord(&mouse_pos), from, precise, bounds);
+
+                // Precise drags are for fine adjustments snapping would undo.
+                if !precise {
+                    let tolerance = SNAP_DISTANCE / view.scale().max();
+                    to = snapping.snap(&mesh, &selection, to, tolerance);
+                }
+
+                if to != from {
+                    let edit = selection.translate_edit(&mesh, to - from, bounds);
+                    history.apply_merged(&mut mesh, edit);
+                }
             }
         }
 
         if is_mouse_button_released(MouseButton::Left) {
-            last_mouse_pos = None;
+            if let Some(start) = rubber_band.take() {
+                let (min, max) = (start.inf(&mouse_pos), start.sup(&mouse_pos));
+
+                selection.extend(mesh.points.iter().enumerate().filter_map(|(idx, point)| {
+                    let spoint = view.ws_coord(&point.position);
+                    let inside = spoint.x >= min.x
+                        && spoint.x <= max.x
+                        && spoint.y >= min.y
+                        && spoint.y <= max.y;
+
+                    inside.then_some(idx)
+                }));
+            }
+
+            drag = None;
+            history.end_merge();
+        }
+
+        if ctrl && is_key_pressed(KeyCode::Z) {
+            if shift {
+                restored |= history.redo(&mut mesh);
+            } else {
+                restored |= history.undo(&mut mesh);
+            }
+
+            // Otherwise the drag would immediately overwrite the restored position.
+            drag = None;
+        }
+
+        if workspace_focused && ctrl && is_key_pressed(KeyCode::A) {
+            selection.extend(0..mesh.points.len());
+        }
+
+        if workspace_focused && !ctrl && is_key_pressed(KeyCode::R) {
+            selection.grow_to_rows(&mesh);
+        }
+
+        if workspace_focused && !ctrl && is_key_pressed(KeyCode::C) {
+            selection.grow_to_columns(&mesh);
+        }
+
+        if workspace_focused && is_key_pressed(KeyCode::Home) {
+            view.reset();
+        }
+
+        if workspace_focused && is_key_pressed(KeyCode::Escape) {
+            selection.clear();
         }
 
-        for w in 0..mesh.width - 1 {
-            for h in 0..mesh.height - 1 {
-                let p00 = mesh.point_at(w, h);
-                let p01 = mesh.point_at(w, h + 1);
-                let p10 = mesh.point_at(w + 1, h);
-                let p11 = mesh.point_at(w + 1, h + 1);
+        if workspace_focused && !selection.is_empty() {
+            let step = if shift {
+                snapping.grid_step
+            } else {
+                snapping.grid_step / 10.0
+            };
+
+            let mut delta = na::Vector2::zeros();
+
+            if is_key_pressed(KeyCode::Left) {
+                delta.x -= step;
+            }
+
+            if is_key_pressed(KeyCode::Right) {
+                delta.x += step;
+            }
+
+            if is_key_pressed(KeyCode::Up) {
+                delta.y -= step;
+            }
+
+            if is_key_pressed(KeyCode::Down) {
+                delta.y += step;
+            }
 
-                let x_coeff = geometric_coefficients(p00, p01, p10, p11, Axis::X);
-                let y_coeff = geometric_coefficients(p00, p01, p10, p11, Axis::Y);
-                let r_coeff = color_coefficients(p00, p01, p10, p11, ColorAxis::R);
-                let g_coeff = color_coefficients(p00, p01, p10, p11, ColorAxis::G);
-                let b_coeff = color_coefficients(p00, p01, p10, p11, ColorAxis::B);
+            if delta != na::Vector2::zeros() {
+                let edit = selection.translate_edit(&mesh, delta, drag_bounds(extended_canvas));
+                history.apply(&mut mesh, edit);
+            }
+        }
+
+        // Undoing a grid change can leave the selection pointing past the last point.
+        selection.retain_valid(&mesh);
+
+        // Colors resampled after the points move join the undo entry of the move, so undoing it
+        // brings the colors sampled at the old positions back with it.
+        if let (Some((image, _)), Some(underlay), true) = (&reference, &underlay, follow_image) {
+            let positions: Vec<_> = mesh.points.iter().map(|point| point.position).collect();
+            let current = Some((positions, blur_radius, aspect, underlay.clone()));
+
+            if sampled_with != current && !restored {
+                let colors = image.sample_colors(&mesh, underlay, aspect, blur_radius);
+
+                if colors
+                    .iter()
+                    .zip(&mesh.points)
+                    .any(|(color, point)| *color != point.color)
+                {
+                    let from = mesh.points.clone();
+                    let to = from
+                        .iter()
+                        .zip(colors)
+                        .map(|(point, color)| {
+                            let mut point = point.clone();
+                            point.color = color;
+                            point
+                        })
+                        .collect();
+
+                    let edit = Edit::SetPoints {
+                        idxs: (0..mesh.points.len()).collect(),
+                        from,
+                        to,
+                    };
 
-                draw_hermite_from_geom(&x_coeff, &y_coeff, (&r_coeff, &g_coeff, &b_coeff), 100);
+                    // Only the points moved since the last sample - anything else is a change of
+                    // its own, like the "Sample colors" button.
+                    let moved = matches!(
+                        (&sampled_with, &current),
+                        (Some((_, blur, aspect, underlay)), Some((_, new_blur, new_aspect, new_underlay)))
+                            if (blur, aspect, underlay) == (new_blur, new_aspect, new_underlay)
+                    );
+                    if moved {
+                        history.amend(&mut mesh, edit);
+                    } else {
+                        history.apply_merged(&mut mesh, edit);
+                    }
+                }
             }
+
+            sampled_with = current;
+        } else {
+            // Frozen colors may get edited, so following again has to resample.
+            sampled_with = None;
         }
 
         next_frame().await;
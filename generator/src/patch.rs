@@ -0,0 +1,270 @@
+use std::fmt;
+
+use nalgebra as na;
+use nalgebra::matrix;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color_coefficients, geometric_coefficients, Axis, ColorAxis, ControlPoint, Mesh, Patch,
+};
+
+/// How the control points shape the patches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PatchKind {
+    /// Ferguson patches - `u_tangent` and `v_tangent` are derivatives at the point.
+    #[default]
+    Hermite,
+    /// Tensor-product cubic Bezier patches - `u_tangent` and `v_tangent` are offsets
+    /// of the neighbouring handles in the control net, a third of the Hermite derivatives.
+    /// `twist` offsets the interior handles, a ninth of the Hermite twist.
+    Bezier,
+    /// Interpolating Catmull-Rom spline surface through all control points, C1 across
+    /// patches. Tangents and twists are ignored - they follow from the neighbouring points.
+    CatmullRom,
+    /// Approximating uniform cubic B-spline surface, C2 across patches. It passes only
+    /// through the corner points of the grid. Tangents and twists are ignored.
+    BSpline,
+}
+
+impl fmt::Display for PatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchKind::Hermite => write!(f, "Hermite"),
+            PatchKind::Bezier => write!(f, "Bezier"),
+            PatchKind::CatmullRom => write!(f, "Catmull-Rom"),
+            PatchKind::BSpline => write!(f, "B-spline"),
+        }
+    }
+}
+
+/// Maps a Hermite vector `[p0, p1, t0, t1]` of a cubic to its Bezier control points.
+const HERMITE_TO_BEZIER: na::Matrix4<f32> = matrix![
+    1.0, 0.0, 0.0,       0.0;
+    1.0, 0.0, 1.0 / 3.0, 0.0;
+    0.0, 1.0, 0.0,      -1.0 / 3.0;
+    0.0, 1.0, 0.0,       0.0;
+];
+
+/// Inverse of `HERMITE_TO_BEZIER`.
+const BEZIER_TO_HERMITE: na::Matrix4<f32> = matrix![
+     1.0, 0.0,  0.0, 0.0;
+     0.0, 0.0,  0.0, 1.0;
+    -3.0, 3.0,  0.0, 0.0;
+     0.0, 0.0, -3.0, 3.0;
+];
+
+/// Maps four consecutive values `[p-1, p0, p1, p2]` of a Catmull-Rom spline to the
+/// Hermite vector `[p0, p1, t0, t1]` of its span between `p0` and `p1`.
+const CATMULL_ROM_TO_HERMITE: na::Matrix4<f32> = matrix![
+     0.0,  1.0, 0.0, 0.0;
+     0.0,  0.0, 1.0, 0.0;
+    -0.5,  0.0, 0.5, 0.0;
+     0.0, -0.5, 0.0, 0.5;
+];
+
+/// Like `CATMULL_ROM_TO_HERMITE`, for a uniform cubic B-spline.
+const B_SPLINE_TO_HERMITE: na::Matrix4<f32> = matrix![
+     1.0 / 6.0, 2.0 / 3.0, 1.0 / 6.0, 0.0;
+     0.0,       1.0 / 6.0, 2.0 / 3.0, 1.0 / 6.0;
+    -0.5,       0.0,       0.5,       0.0;
+     0.0,      -0.5,       0.0,       0.5;
+];
+
+/// Converts geometric coefficients in the layout of `geometric_coefficients` to the 4x4
+/// Bezier control net of the same patch (for one coordinate). Rows of both step along h, columns along w.
+pub fn hermite_to_bezier(coefficients: &na::Matrix4<f32>) -> na::Matrix4<f32> {
+    HERMITE_TO_BEZIER * coefficients * HERMITE_TO_BEZIER.transpose()
+}
+
+/// Inverse of `hermite_to_bezier`.
+pub fn bezier_to_hermite(net: &na::Matrix4<f32>) -> na::Matrix4<f32> {
+    BEZIER_TO_HERMITE * net * BEZIER_TO_HERMITE.transpose()
+}
+
+/// Bezier control net of a patch for one coordinate, with handles at `position ± tangent`.
+/// Interior points complete parallelograms with the corners and their handles, offset by the twist.
+fn bezier_net(
+    p00: &ControlPoint,
+    p01: &ControlPoint,
+    p10: &ControlPoint,
+    p11: &ControlPoint,
+    axis: Axis,
+) -> na::Matrix4<f32> {
+    let c = |v: na::Vector2<f32>| match axis {
+        Axis::X => v.x,
+        Axis::Y => v.y,
+    };
+
+    // Each corner with its handles towards the other corners of the patch -
+    // `u_tangent` steps along w, `v_tangent` along h.
+    let corner = |p: &ControlPoint, w_sign: f32, h_sign: f32| {
+        let l = c(p.position);
+        let along_w = l + w_sign * c(p.u_tangent);
+        let along_h = l + h_sign * c(p.v_tangent);
+        let interior = along_w + along_h - l + w_sign * h_sign * c(p.twist);
+        (l, along_w, along_h, interior)
+    };
+
+    let (l00, w00, h00, i00) = corner(p00, 1.0, 1.0);
+    let (l01, w01, h01, i01) = corner(p01, 1.0, -1.0);
+    let (l10, w10, h10, i10) = corner(p10, -1.0, 1.0);
+    let (l11, w11, h11, i11) = corner(p11, -1.0, -1.0);
+
+    matrix![
+        l00, w00, w10, l10;
+        h00, i00, i10, h10;
+        h01, i01, i11, h11;
+        l01, w01, w11, l11;
+    ]
+}
+
+/// `value` of the control point at `(w, h)`. Past the edges of the grid values are continued
+/// linearly by one point, so spline surfaces end on the boundary and keep straight edges straight.
+fn extended_value(mesh: &Mesh, w: isize, h: isize, value: &impl Fn(&ControlPoint) -> f32) -> f32 {
+    let (width, height) = (mesh.width as isize, mesh.height as isize);
+
+    if w < 0 {
+        2.0 * extended_value(mesh, 0, h, value) - extended_value(mesh, 1, h, value)
+    } else if w >= width {
+        2.0 * extended_value(mesh, width - 1, h, value) - extended_value(mesh, width - 2, h, value)
+    } else if h < 0 {
+        2.0 * extended_value(mesh, w, 0, value) - extended_value(mesh, w, 1, value)
+    } else if h >= height {
+        2.0 * extended_value(mesh, w, height - 1, value)
+            - extended_value(mesh, w, height - 2, value)
+    } else {
+        value(mesh.point_at(w as usize, h as usize))
+    }
+}
+
+/// Hermite coefficients of the spline patch with top left corner at `(w, h)`, from the 4x4
+/// neighbourhood of control points around it.
+fn spline_coefficients(
+    mesh: &Mesh,
+    w: usize,
+    h: usize,
+    basis: &na::Matrix4<f32>,
+    value: impl Fn(&ControlPoint) -> f32,
+) -> na::Matrix4<f32> {
+    let neighbourhood = na::Matrix4::from_fn(|row, col| {
+        extended_value(mesh, (w + col) as isize - 1, (h + row) as isize - 1, &value)
+    });
+
+    basis * neighbourhood * basis.transpose()
+}
+
+fn coordinate(axis: Axis) -> impl Fn(&ControlPoint) -> f32 {
+    move |p| match axis {
+        Axis::X => p.position.x,
+        Axis::Y => p.position.y,
+    }
+}
+
+impl Mesh {
+    /// Geometric coefficients of the patch with top left corner at `(w, h)`,
+    /// in the Hermite form `ferguson_patch_pt` evaluates for either patch kind.
+    pub fn patch_coefficients(&self, w: usize, h: usize, axis: Axis) -> na::Matrix4<f32> {
+        let p00 = self.point_at(w, h);
+        let p01 = self.point_at(w, h + 1);
+        let p10 = self.point_at(w + 1, h);
+        let p11 = self.point_at(w + 1, h + 1);
+
+        match self.kind {
+            PatchKind::Hermite => geometric_coefficients(p00, p01, p10, p11, axis),
+            PatchKind::Bezier => bezier_to_hermite(&bezier_net(p00, p01, p10, p11, axis)),
+            PatchKind::CatmullRom => {
+                spline_coefficients(self, w, h, &CATMULL_ROM_TO_HERMITE, coordinate(axis))
+            }
+            PatchKind::BSpline => {
+                spline_coefficients(self, w, h, &B_SPLINE_TO_HERMITE, coordinate(axis))
+            }
+        }
+    }
+
+    /// The patch with top left corner at `(w, h)`, with its coefficients precomputed.
+    pub fn patch(&self, w: usize, h: usize) -> Patch {
+        Patch::new(
+            &self.patch_coefficients(w, h, Axis::X),
+            &self.patch_coefficients(w, h, Axis::Y),
+            (
+                &self.patch_color_coefficients(w, h, ColorAxis::R),
+                &self.patch_color_coefficients(w, h, ColorAxis::G),
+                &self.patch_color_coefficients(w, h, ColorAxis::B),
+            ),
+        )
+    }
+
+    /// Color coefficients of the patch with top left corner at `(w, h)`, for `ferguson_patch_col`.
+    /// Spline kinds blend colors with the same basis as positions.
+    pub fn patch_color_coefficients(
+        &self,
+        w: usize,
+        h: usize,
+        color: ColorAxis,
+    ) -> na::Matrix4<f32> {
+        let channel = move |p: &ControlPoint| match color {
+            ColorAxis::R => p.color.x,
+            ColorAxis::G => p.color.y,
+            ColorAxis::B => p.color.z,
+        };
+
+        match self.kind {
+            PatchKind::Hermite | PatchKind::Bezier => color_coefficients(
+                self.point_at(w, h),
+                self.point_at(w, h + 1),
+                self.point_at(w + 1, h),
+                self.point_at(w + 1, h + 1),
+                color,
+            ),
+            PatchKind::CatmullRom => {
+                spline_coefficients(self, w, h, &CATMULL_ROM_TO_HERMITE, channel)
+            }
+            PatchKind::BSpline => spline_coefficients(self, w, h, &B_SPLINE_TO_HERMITE, channel),
+        }
+    }
+
+    /// The mesh with patches of another kind. Between Hermite and Bezier the surface stays the same.
+    ///
+    /// Spline kinds ignore tangents, so switching to them changes the surface. Leaving one sets
+    /// tangents and twists to the spline's derivatives at the control points, which keeps
+    /// a Catmull-Rom surface the same.
+    pub fn with_kind(&self, kind: PatchKind) -> Mesh {
+        let mut mesh = self.clone();
+        mesh.kind = kind;
+
+        let mut from = self.kind;
+        if matches!(from, PatchKind::CatmullRom | PatchKind::BSpline) {
+            for h in 0..self.height {
+                for w in 0..self.width {
+                    let (wi, hi) = (w as isize, h as isize);
+                    let at = |dw: isize, dh: isize| {
+                        let e = |axis| extended_value(self, wi + dw, hi + dh, &coordinate(axis));
+                        na::Vector2::new(e(Axis::X), e(Axis::Y))
+                    };
+
+                    // Central differences, and the mixed one for the twist.
+                    let point = &mut mesh.points[h * self.width + w];
+                    point.u_tangent = (at(1, 0) - at(-1, 0)) / 2.0;
+                    point.v_tangent = (at(0, 1) - at(0, -1)) / 2.0;
+                    point.twist = (at(1, 1) - at(1, -1) - at(-1, 1) + at(-1, -1)) / 4.0;
+                }
+            }
+
+            from = PatchKind::Hermite;
+        }
+
+        let scale = match (from, kind) {
+            (PatchKind::Hermite, PatchKind::Bezier) => 1.0 / 3.0,
+            (PatchKind::Bezier, PatchKind::Hermite) => 3.0,
+            _ => 1.0,
+        };
+
+        for point in &mut mesh.points {
+            point.u_tangent *= scale;
+            point.v_tangent *= scale;
+            point.twist *= scale * scale;
+        }
+
+        mesh
+    }
+}
@@ -0,0 +1,168 @@
+use std::io::Write;
+
+use nalgebra as na;
+
+use crate::Mesh;
+
+/// Side of the square page in points (1/72 inch). The unit square of the mesh fills the page.
+const PAGE_SIZE: f32 = 576.0;
+
+/// Control points of a type 7 patch in the order PDF expects them, as `(row, column)` of
+/// the Bezier net - around the boundary starting at the first corner, then the inside.
+const POINT_ORDER: [(usize, usize); 16] = [
+    (0, 0),
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (1, 3),
+    (2, 3),
+    (3, 3),
+    (3, 2),
+    (3, 1),
+    (3, 0),
+    (2, 0),
+    (1, 0),
+    (1, 1),
+    (1, 2),
+    (2, 2),
+    (2, 1),
+];
+
+/// Corners in the order of the four patch colors, as `(u, v)`.
+const CORNER_ORDER: [(f32, f32); 4] = [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)];
+
+/// A patch of the shading in page space, `points` and `colors` in PDF order.
+struct ShadingPatch {
+    points: [na::Vector2<f32>; 16],
+    colors: [na::Vector3<f32>; 4],
+}
+
+/// The mesh as a one-page PDF with a tensor-product patch mesh shading (type 7), a vector gradient
+/// which print workflows can render at any resolution.
+///
+/// Each patch becomes its Bezier control net plus the colors at its corners. PDF interpolates colors
+/// bilinearly between those, so every patch is split into `(subdivs + 1)^2` smaller ones to follow
+/// the cubic colors more closely, like `construct_mesh` does with triangles. Patches are painted in
+/// the order of `construct_mesh`, but PDF doesn't cull anything, so folds show their back faces too.
+pub fn pdf(mesh: &Mesh, subdivs: usize) -> Vec<u8> {
+    let patches = shading_patches(mesh, subdivs);
+
+    let (min, max) = patches.iter().flat_map(|patch| patch.points).fold(
+        (na::Vector2::repeat(f32::MAX), na::Vector2::repeat(f32::MIN)),
+        |(min, max), point| (min.inf(&point), max.sup(&point)),
+    );
+    // Coordinates are stored as fractions of this range, which mustn't be empty.
+    let max = max.zip_map(&min, |max, min| max.max(min + 1.0));
+
+    let shading = format!(
+        "/ShadingType 7 /ColorSpace /DeviceRGB /BitsPerCoordinate 32 /BitsPerComponent 16 /BitsPerFlag 8 \
+         /Decode [{} {} {} {} 0 1 0 1 0 1]",
+        min.x, max.x, min.y, max.y
+    );
+    let content = "/Sh0 sh";
+
+    let objects = [
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_SIZE} {PAGE_SIZE}] \
+             /Resources << /Shading << /Sh0 5 0 R >> >> /Contents 4 0 R >>"
+        )
+        .into_bytes(),
+        stream("", content.as_bytes()),
+        stream(&shading, &shading_data(&patches, min, max)),
+    ];
+
+    // The second line marks the file as binary for tools which guess it from the first bytes.
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        writeln!(pdf, "{} 0 obj", i + 1).unwrap();
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).unwrap();
+    for offset in offsets {
+        writeln!(pdf, "{offset:010} 00000 n ").unwrap();
+    }
+    write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    )
+    .unwrap();
+
+    pdf
+}
+
+/// Stream object with the `entries` of its dictionary next to `/Length`.
+fn stream(entries: &str, data: &[u8]) -> Vec<u8> {
+    let dictionary = format!("{entries} /Length {}", data.len());
+    let mut stream = format!("<< {} >>\nstream\n", dictionary.trim_start()).into_bytes();
+    stream.extend_from_slice(data);
+    stream.extend_from_slice(b"\nendstream");
+    stream
+}
+
+fn shading_patches(mesh: &Mesh, subdivs: usize) -> Vec<ShadingPatch> {
+    let steps = subdivs + 1;
+    let size = 1.0 / steps as f32;
+    let page = |x: f32, y: f32| na::Vector2::new(x * PAGE_SIZE, (1.0 - y) * PAGE_SIZE);
+
+    let mut patches = Vec::with_capacity((mesh.width - 1) * (mesh.height - 1) * steps * steps);
+
+    for w in 0..mesh.width - 1 {
+        for h in 0..mesh.height - 1 {
+            let patch = mesh.patch(w, h);
+
+            for i in 0..steps {
+                for j in 0..steps {
+                    let (u, v) = (i as f32 * size, j as f32 * size);
+                    let (x, y) = patch.part(u, v, size).bezier_net();
+
+                    patches.push(ShadingPatch {
+                        points: POINT_ORDER
+                            .map(|(row, column)| page(x[(row, column)], y[(row, column)])),
+                        colors: CORNER_ORDER
+                            .map(|(du, dv)| patch.color(u + du * size, v + dv * size)),
+                    });
+                }
+            }
+        }
+    }
+
+    patches
+}
+
+/// Packed patches of the shading stream, coordinates mapped from `[min, max]` onto the whole `u32` range.
+fn shading_data(patches: &[ShadingPatch], min: na::Vector2<f32>, max: na::Vector2<f32>) -> Vec<u8> {
+    let coordinate = |value: f32, min: f32, max: f32| {
+        let fraction = (value as f64 - min as f64) / (max as f64 - min as f64);
+        (fraction.clamp(0.0, 1.0) * u32::MAX as f64).round() as u32
+    };
+    let component = |value: f32| (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+
+    let mut data = Vec::with_capacity(patches.len() * (1 + 16 * 8 + 4 * 6));
+
+    for patch in patches {
+        // Every patch starts a new one, none continues the edge of the previous patch.
+        data.push(0);
+
+        for point in patch.points {
+            data.extend_from_slice(&coordinate(point.x, min.x, max.x).to_be_bytes());
+            data.extend_from_slice(&coordinate(point.y, min.y, max.y).to_be_bytes());
+        }
+
+        for color in patch.colors {
+            for value in color.iter() {
+                data.extend_from_slice(&component(*value).to_be_bytes());
+            }
+        }
+    }
+
+    data
+}
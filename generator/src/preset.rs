@@ -0,0 +1,151 @@
+use std::path::PathBuf;
+
+use nalgebra as na;
+
+use crate::document::DocumentError;
+use crate::Mesh;
+
+/// A named starting layout.
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub name: String,
+    pub mesh: Mesh,
+}
+
+pub(crate) fn rgb(hex: u32) -> na::Vector3<f32> {
+    na::Vector3::new(
+        ((hex >> 16) & 0xff) as f32 / 255.0,
+        ((hex >> 8) & 0xff) as f32 / 255.0,
+        (hex & 0xff) as f32 / 255.0,
+    )
+}
+
+/// Regular grid with row-major `colors` and some points moved to `(w, h, position)`.
+fn preset(
+    name: &str,
+    width: usize,
+    height: usize,
+    colors: &[u32],
+    moved: &[(usize, usize, [f32; 2])],
+) -> Preset {
+    let mut mesh = Mesh::new(width, height, colors.iter().copied().map(rgb).collect());
+
+    for &(w, h, [x, y]) in moved {
+        mesh.points[h * width + w].position = na::Vector2::new(x, y);
+    }
+
+    Preset {
+        name: name.to_string(),
+        mesh,
+    }
+}
+
+/// Layouts shipped with the generator.
+#[rustfmt::skip]
+pub fn builtin() -> Vec<Preset> {
+    vec![
+        // The SwiftUI `MeshGradient` sample from WWDC 2024.
+        preset("WWDC", 3, 3, &[
+            0x000000, 0x000000, 0x000000,
+            0x0000ff, 0x0000ff, 0x0000ff,
+            0x00ff00, 0x00ff00, 0x00ff00,
+        ], &[(1, 1, [0.9, 0.3])]),
+        preset("Four corners", 2, 2, &[
+            0xff3b30, 0xffcc00,
+            0x007aff, 0x34c759,
+        ], &[]),
+        preset("Aurora", 4, 3, &[
+            0x020b1f, 0x061a3a, 0x0b1f4a, 0x020b1f,
+            0x0f9b8e, 0x3ddc97, 0x7b2ff7, 0x1b3a6b,
+            0x031024, 0x0a4d68, 0x12305a, 0x031024,
+        ], &[(1, 1, [0.28, 0.62]), (2, 1, [0.7, 0.38])]),
+        preset("Sunset", 3, 4, &[
+            0x1d1a4f, 0x2b1e5c, 0x1d1a4f,
+            0x8e3b8f, 0xc2467a, 0x8e3b8f,
+            0xf06543, 0xff7e4a, 0xf06543,
+            0xffc857, 0xffd97a, 0xffc857,
+        ], &[(1, 1, [0.5, 0.28]), (1, 2, [0.5, 0.72])]),
+        preset("Ocean", 4, 4, &[
+            0xe0f7fa, 0xb2ebf2, 0x80deea, 0x4dd0e1,
+            0x4fc3f7, 0x29b6f6, 0x0288d1, 0x0277bd,
+            0x1565c0, 0x0d47a1, 0x1a237e, 0x0d1b5e,
+            0x0a1440, 0x081030, 0x060c24, 0x040818,
+        ], &[(1, 1, [0.4, 0.25]), (2, 1, [0.62, 0.4]), (1, 2, [0.3, 0.7]), (2, 2, [0.7, 0.62])]),
+        preset("Pastel", 5, 5, &[
+            0xffd1dc, 0xffe4e1, 0xfff0d4, 0xe8f6d4, 0xd4f0f0,
+            0xffc8dd, 0xf9d5e5, 0xfde2c8, 0xd8f3dc, 0xcde7f0,
+            0xe4c1f9, 0xf1c0e8, 0xfbe7c6, 0xb8e0d2, 0xa9def9,
+            0xd0bdf4, 0xe2c2ff, 0xcfbaf0, 0xa3c4f3, 0x90dbf4,
+            0xc3b1e1, 0xb9a6f0, 0xa0c4ff, 0x8eecf5, 0x98f5e1,
+        ], &[(2, 2, [0.58, 0.44]), (1, 3, [0.2, 0.8]), (3, 1, [0.8, 0.2])]),
+    ]
+}
+
+/// Where user presets are stored - `mesh-gradient/presets` in the platform data directory.
+pub fn user_dir() -> Option<PathBuf> {
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })?;
+
+    Some(data.join("mesh-gradient").join("presets"))
+}
+
+/// Documents saved with `save_user`, sorted by name. Files which can't be read are skipped.
+pub fn user() -> Vec<Preset> {
+    let Some(entries) = user_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    let mut presets: Vec<_> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+
+            Some(Preset {
+                name: path.file_stem()?.to_string_lossy().into_owned(),
+                mesh: Mesh::load(&path).ok()?,
+            })
+        })
+        .collect();
+
+    presets.sort_by(|a, b| a.name.cmp(&b.name));
+    presets
+}
+
+/// Saves `mesh` as a user preset. Characters which aren't safe in file names are replaced, and an
+/// existing preset of the same name is never overwritten.
+pub fn save_user(name: &str, mesh: &Mesh) -> Result<PathBuf, DocumentError> {
+    let dir = user_dir().ok_or_else(|| {
+        DocumentError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no user data directory",
+        ))
+    })?;
+    std::fs::create_dir_all(&dir)?;
+
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            c if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' => c,
+            _ => '-',
+        })
+        .collect();
+    let name = if name.is_empty() { "untitled" } else { &name };
+
+    let path = dir.join(format!("{name}.json"));
+    if path.exists() {
+        return Err(DocumentError::Io(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("a preset named \"{name}\" already exists"),
+        )));
+    }
+    mesh.save(&path)?;
+
+    Ok(path)
+}
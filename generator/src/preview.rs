@@ -0,0 +1,46 @@
+use generator::fold::{folded_patches, Fold};
+use generator::{construct_mesh, Buffers, Mesh};
+
+/// Tessellation and folds of the mesh shown in the workspace. Both are too slow to redo every
+/// frame for large meshes, so they are kept until the mesh or the subdivision count changes.
+///
+/// The mesh is compared as a whole rather than by history revision, because following
+/// a reference image recolors it without going through the history.
+pub struct Preview {
+    mesh: Mesh,
+    subdivs: usize,
+    buffers: Buffers,
+    folds: Vec<(usize, usize, Fold)>,
+}
+
+impl Preview {
+    pub fn new(mesh: &Mesh, subdivs: usize) -> Self {
+        Self {
+            mesh: mesh.clone(),
+            subdivs,
+            buffers: construct_mesh(mesh, subdivs),
+            folds: folded_patches(mesh),
+        }
+    }
+
+    /// Recomputes everything if `mesh` or `subdivs` differ from the last call.
+    pub fn update(&mut self, mesh: &Mesh, subdivs: usize) {
+        if self.subdivs != subdivs || self.mesh != *mesh {
+            *self = Self::new(mesh, subdivs);
+        }
+    }
+
+    pub fn subdivs(&self) -> usize {
+        self.subdivs
+    }
+
+    /// `construct_mesh` output.
+    pub fn buffers(&self) -> &Buffers {
+        &self.buffers
+    }
+
+    /// `folded_patches` output.
+    pub fn folds(&self) -> &[(usize, usize, Fold)] {
+        &self.folds
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt;
+
+use nalgebra as na;
+
+use crate::fold::folded_patches;
+use crate::preset::rgb;
+use crate::{Derivatives, Mesh};
+
+/// Colors of `Harmony::Palette`.
+const PALETTE: [u32; 6] = [0x264653, 0x2a9d8f, 0x8ab17d, 0xe9c46a, 0xf4a261, 0xe76f51];
+
+/// How the colors of a random mesh relate to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Harmony {
+    /// Hues within 30 degrees of a base hue.
+    Analogous,
+    /// A base hue and its opposite.
+    Complementary,
+    /// Three hues evenly spread around the color wheel.
+    Triadic,
+    /// Colors drawn from a fixed palette.
+    Palette,
+}
+
+impl Harmony {
+    /// Reads the name `Display` writes.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "analogous" => Some(Harmony::Analogous),
+            "complementary" => Some(Harmony::Complementary),
+            "triadic" => Some(Harmony::Triadic),
+            "palette" => Some(Harmony::Palette),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Harmony {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Harmony::Analogous => write!(f, "analogous"),
+            Harmony::Complementary => write!(f, "complementary"),
+            Harmony::Triadic => write!(f, "triadic"),
+            Harmony::Palette => write!(f, "palette"),
+        }
+    }
+}
+
+/// Everything `random_mesh` depends on, written as `{width}x{height}-{harmony}-{text}` so a whole
+/// gradient can be shared as one string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seed {
+    pub width: usize,
+    pub height: usize,
+    pub harmony: Harmony,
+    pub text: String,
+}
+
+impl Seed {
+    /// Reads a seed written by `Display`. Anything else is just seed text and gives `None`.
+    pub fn parse(seed: &str) -> Option<Self> {
+        let mut parts = seed.splitn(3, '-');
+        let (width, height) = parts.next()?.split_once('x')?;
+        let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+        let harmony = Harmony::from_name(parts.next()?)?;
+        let text = parts.next()?.to_string();
+
+        (width >= 2 && height >= 2).then_some(Seed {
+            width,
+            height,
+            harmony,
+            text,
+        })
+    }
+
+    pub fn mesh(&self) -> Mesh {
+        random_mesh(&self.text, self.width, self.height, &self.harmony)
+    }
+}
+
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{}-{}-{}",
+            self.width, self.height, self.harmony, self.text
+        )
+    }
+}
+
+/// SplitMix64 - tiny, but good enough for picking colors and offsets.
+struct Rng(u64);
+
+impl Rng {
+    /// Seeds from a string with FNV-1a, so any text can be shared as a seed.
+    fn from_seed(seed: &str) -> Self {
+        let hash = seed.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+
+        Self(hash)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    fn index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
+/// `hue` in degrees, `saturation` and `lightness` in `[0, 1]`.
+fn hsl(hue: f32, saturation: f32, lightness: f32) -> na::Vector3<f32> {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    let m = lightness - chroma / 2.0;
+    na::Vector3::new(r + m, g + m, b + m)
+}
+
+/// Largest share of a cell interior points may be moved by.
+const MAX_JITTER: f32 = 0.35;
+/// Number of samples along each parameter when checking the Jacobian of a patch.
+const JACOBIAN_SAMPLES: usize = 16;
+/// Smallest Jacobian a patch may have anywhere, relative to its mean.
+const MIN_JACOBIAN_RATIO: f32 = 0.25;
+
+/// Random mesh for `seed` - the same seed, size and harmony always give the same mesh.
+///
+/// Interior points are jittered within their cells. Whenever that folds a patch or squeezes it towards
+/// a cusp, all offsets are halved until it doesn't, down to the regular grid if it has to.
+pub fn random_mesh(seed: &str, width: usize, height: usize, harmony: &Harmony) -> Mesh {
+    let mut rng = Rng::from_seed(seed);
+
+    let base_hue = rng.range(0.0, 360.0);
+    let hues: Vec<f32> = match harmony {
+        Harmony::Analogous => vec![base_hue - 30.0, base_hue, base_hue + 30.0],
+        Harmony::Complementary => vec![base_hue, base_hue + 180.0],
+        Harmony::Triadic => vec![base_hue, base_hue + 120.0, base_hue + 240.0],
+        Harmony::Palette => Vec::new(),
+    };
+
+    let colors = (0..width * height)
+        .map(|_| match harmony {
+            Harmony::Palette => rgb(PALETTE[rng.index(PALETTE.len())]),
+            _ => hsl(
+                hues[rng.index(hues.len())] + rng.range(-10.0, 10.0),
+                rng.range(0.55, 0.9),
+                rng.range(0.35, 0.75),
+            ),
+        })
+        .collect();
+
+    let offsets: Vec<na::Vector2<f32>> = (0..width * height)
+        .map(|_| {
+            na::Vector2::new(
+                rng.range(-MAX_JITTER, MAX_JITTER) / (width - 1) as f32,
+                rng.range(-MAX_JITTER, MAX_JITTER) / (height - 1) as f32,
+            )
+        })
+        .collect();
+
+    let regular = Mesh::new(width, height, colors);
+    let mut scale = 1.0;
+
+    while scale > 1e-3 {
+        let mut mesh = regular.clone();
+
+        for h in 1..height - 1 {
+            for w in 1..width - 1 {
+                let idx = h * width + w;
+                mesh.points[idx].position += offsets[idx] * scale;
+            }
+        }
+
+        if folded_patches(&mesh).is_empty() && is_well_conditioned(&mesh) {
+            return mesh;
+        }
+
+        scale /= 2.0;
+    }
+
+    regular
+}
+
+/// Whether the Jacobian of every patch stays within `MIN_JACOBIAN_RATIO` of its mean. Patches right
+/// next to a cusp don't fold, but the triangles of their tessellations can flip, at fine subdivision
+/// counts as well as coarse ones.
+fn is_well_conditioned(mesh: &Mesh) -> bool {
+    let samples: Vec<f32> = (0..=JACOBIAN_SAMPLES)
+        .map(|i| i as f32 / JACOBIAN_SAMPLES as f32)
+        .collect();
+
+    (0..mesh.height - 1).all(|h| {
+        (0..mesh.width - 1).all(|w| {
+            let jacobians: Vec<f32> = mesh
+                .patch(w, h)
+                .derivative_grid(&samples, &samples)
+                .into_iter()
+                .map(
+                    |Derivatives {
+                         position_du: du,
+                         position_dv: dv,
+                         ..
+                     }| dv.x * du.y - du.x * dv.y,
+                )
+                .collect();
+
+            let mean = jacobians.iter().sum::<f32>() / jacobians.len() as f32;
+            jacobians
+                .iter()
+                .all(|&jacobian| jacobian >= MIN_JACOBIAN_RATIO * mean)
+        })
+    })
+}
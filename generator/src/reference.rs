@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::Mesh;
+
+/// Where a reference image lies in mesh space and how it is shown behind the mesh.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Underlay {
+    pub path: String,
+    pub opacity: f32,
+    /// Side of the square the image is fitted into, in mesh space units. At 1 it covers
+    /// the unit square, which is shown stretched to the design aspect ratio.
+    pub scale: f32,
+    /// Mesh space position of the top left corner of that square.
+    pub offset: na::Vector2<f32>,
+}
+
+impl Underlay {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            opacity: 0.5,
+            scale: 1.0,
+            offset: na::Vector2::zeros(),
+        }
+    }
+
+    /// Mesh space top left corner and size of `image`, on a canvas shown at the width / height
+    /// ratio `aspect`. The image keeps its own ratio on screen and is centered in the square
+    /// at `offset`, touching two of its sides.
+    pub fn image_rect(
+        &self,
+        image: &ReferenceImage,
+        aspect: f32,
+    ) -> (na::Vector2<f32>, na::Vector2<f32>) {
+        // On screen the square is `aspect` times wider than high.
+        let (width, height) = (image.width as f32 / aspect, image.height as f32);
+        let size = na::Vector2::new(width, height) * self.scale / width.max(height);
+
+        (
+            self.offset + (na::Vector2::repeat(self.scale) - size) / 2.0,
+            size,
+        )
+    }
+
+    /// Maps a mesh space position onto `image`, whose rectangle from `image_rect` becomes the unit square.
+    pub fn to_image(
+        &self,
+        image: &ReferenceImage,
+        aspect: f32,
+        position: na::Vector2<f32>,
+    ) -> na::Vector2<f32> {
+        let (origin, size) = self.image_rect(image, aspect);
+
+        (position - origin).component_div(&size)
+    }
+}
+
+/// An image control point colors can be sampled from. Positions are relative to the image,
+/// which spans the unit square - see `Underlay::to_image`.
+#[derive(Debug, Clone)]
+pub struct ReferenceImage {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major RGB, in `[0, 1]`.
+    pub pixels: Vec<na::Vector3<f32>>,
+}
+
+impl ReferenceImage {
+    /// Reads a PNG or JPEG file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, image::ImageError> {
+        let image = image::open(path)?.into_rgb32f();
+
+        Ok(Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels: image
+                .pixels()
+                .map(|pixel| na::Vector3::new(pixel[0], pixel[1], pixel[2]))
+                .collect(),
+        })
+    }
+
+    fn pixel(&self, x: isize, y: isize) -> na::Vector3<f32> {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+
+        self.pixels[y * self.width + x]
+    }
+
+    /// Color at a position, bilinearly interpolated. Positions outside
+    /// of the unit square get the color of the nearest edge.
+    ///
+    /// With a non-zero `blur_radius` (in pixels) the colors within that distance are averaged instead.
+    pub fn color_at(&self, position: na::Vector2<f32>, blur_radius: f32) -> na::Vector3<f32> {
+        let x = position.x * self.width as f32 - 0.5;
+        let y = position.y * self.height as f32 - 0.5;
+
+        if blur_radius < 0.5 {
+            let (x0, y0) = (x.floor(), y.floor());
+            let (fx, fy) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as isize, y0 as isize);
+
+            return self.pixel(x0, y0) * (1.0 - fx) * (1.0 - fy)
+                + self.pixel(x0 + 1, y0) * fx * (1.0 - fy)
+                + self.pixel(x0, y0 + 1) * (1.0 - fx) * fy
+                + self.pixel(x0 + 1, y0 + 1) * fx * fy;
+        }
+
+        let r = blur_radius.ceil() as isize;
+        let (cx, cy) = (x.round() as isize, y.round() as isize);
+
+        let mut sum = na::Vector3::zeros();
+        let mut count = 0;
+
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if ((dx * dx + dy * dy) as f32) <= blur_radius * blur_radius {
+                    sum += self.pixel(cx + dx, cy + dy);
+                    count += 1;
+                }
+            }
+        }
+
+        sum / count as f32
+    }
+
+    /// Colors of all control points of `mesh`, sampled at their positions with the image placed
+    /// by `underlay` on a canvas of `aspect`.
+    pub fn sample_colors(
+        &self,
+        mesh: &Mesh,
+        underlay: &Underlay,
+        aspect: f32,
+        blur_radius: f32,
+    ) -> Vec<na::Vector3<f32>> {
+        mesh.points
+            .iter()
+            .map(|point| {
+                self.color_at(underlay.to_image(self, aspect, point.position), blur_radius)
+            })
+            .collect()
+    }
+}
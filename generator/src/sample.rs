@@ -0,0 +1,221 @@
+use nalgebra as na;
+
+use crate::{Mesh, Patch};
+
+/// The domain of a patch is split into quarters this many times, keeping only the parts whose
+/// bounding box contains the sampled location. Newton starts from the center of each part left,
+/// so it finds all points of a folded patch which land on the location.
+const DEPTH: usize = 6;
+const NEWTON_ITERATIONS: usize = 20;
+/// Distance (in mesh space) from the sampled location at which Newton counts as converged.
+const TOLERANCE: f32 = 1e-5;
+/// Newton steps may leave the patch by this much in u and v, so they can come back
+/// after overshooting near an edge.
+const MARGIN: f32 = 0.5;
+
+/// The gradient at a location of the mesh, see `Mesh::sample`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    /// Top left corner `(w, h)` of the patch.
+    pub patch: (usize, usize),
+    /// Parameters within the patch, u along h and v along w as in `ferguson_patch_pt`.
+    pub u: f32,
+    pub v: f32,
+    pub color: na::Vector3<f32>,
+}
+
+struct IndexedPatch {
+    w: usize,
+    h: usize,
+    patch: Patch,
+    min: na::Vector2<f32>,
+    max: na::Vector2<f32>,
+}
+
+/// Answers `Mesh::sample` queries for one state of a mesh. Patches are binned by their bounding
+/// boxes into a uniform grid with one cell per patch, so a query only inverts the patches near it.
+pub struct Sampler {
+    patches: Vec<IndexedPatch>,
+    min: na::Vector2<f32>,
+    cell_size: na::Vector2<f32>,
+    columns: usize,
+    rows: usize,
+    /// Indexes into `patches` overlapping each cell, `cells[row * columns + column]`, in drawing order.
+    cells: Vec<Vec<usize>>,
+}
+
+impl Sampler {
+    pub fn new(mesh: &Mesh) -> Self {
+        // Same order as `construct_mesh` draws the patches in.
+        let patches: Vec<_> = (0..mesh.width - 1)
+            .flat_map(|w| (0..mesh.height - 1).map(move |h| (w, h)))
+            .map(|(w, h)| {
+                let patch = mesh.patch(w, h);
+                let (min, max) = bounds(&patch);
+
+                IndexedPatch {
+                    w,
+                    h,
+                    patch,
+                    min,
+                    max,
+                }
+            })
+            .collect();
+
+        let min = patches
+            .iter()
+            .fold(na::Vector2::repeat(f32::MAX), |min, p| min.inf(&p.min));
+        let max = patches
+            .iter()
+            .fold(na::Vector2::repeat(f32::MIN), |max, p| max.sup(&p.max));
+
+        let (columns, rows) = (mesh.width - 1, mesh.height - 1);
+        let cell_size = (max - min)
+            .component_div(&na::Vector2::new(columns as f32, rows as f32))
+            .map(|size| size.max(f32::EPSILON));
+
+        let mut sampler = Self {
+            patches,
+            min,
+            cell_size,
+            columns,
+            rows,
+            cells: vec![Vec::new(); columns * rows],
+        };
+
+        for (i, patch) in sampler.patches.iter().enumerate() {
+            let (column_min, row_min) = sampler.cell(patch.min);
+            let (column_max, row_max) = sampler.cell(patch.max);
+
+            for row in row_min..=row_max {
+                for column in column_min..=column_max {
+                    sampler.cells[row * sampler.columns + column].push(i);
+                }
+            }
+        }
+
+        sampler
+    }
+
+    /// Cell containing `point`, clamped to the grid.
+    fn cell(&self, point: na::Vector2<f32>) -> (usize, usize) {
+        let cell = (point - self.min).component_div(&self.cell_size);
+
+        (
+            (cell.x.max(0.0) as usize).min(self.columns - 1),
+            (cell.y.max(0.0) as usize).min(self.rows - 1),
+        )
+    }
+
+    /// See `Mesh::sample`.
+    pub fn sample(&self, x: f32, y: f32) -> Option<Sample> {
+        let target = na::Vector2::new(x, y);
+        let (column, row) = self.cell(target);
+
+        // Later patches are drawn over earlier ones.
+        self.cells[row * self.columns + column]
+            .iter()
+            .rev()
+            .map(|&i| &self.patches[i])
+            .filter(|p| (p.min.x..=p.max.x).contains(&x) && (p.min.y..=p.max.y).contains(&y))
+            .find_map(|p| {
+                let (u, v) = visible_parameters(&p.patch, target)?;
+
+                Some(Sample {
+                    patch: (p.w, p.h),
+                    u,
+                    v,
+                    color: p.patch.color(u, v),
+                })
+            })
+    }
+}
+
+impl Mesh {
+    /// The patch, its parameters and the color at `(x, y)` in mesh space, where the unit square is the exported frame.
+    ///
+    /// Returns `None` outside of the mesh. Where it folds over, the result is what the visualizer shows:
+    /// back-facing parts (with a negative Jacobian, see `fold`) are culled and never returned, and of the
+    /// rest the last one drawn wins - the last patch in `construct_mesh` order, then the largest u and v.
+    /// A location covered only by back-facing parts is a hole, so it gives `None` too. Within about
+    /// a 1/64 of a patch from a crease, where the Jacobian changes sign, the front face may be missed.
+    ///
+    /// Builds the spatial index on every call, use `Sampler` for many queries on the same mesh.
+    pub fn sample(&self, x: f32, y: f32) -> Option<Sample> {
+        Sampler::new(self).sample(x, y)
+    }
+}
+
+/// Bounding box of the patch. Its Bezier control net contains the whole patch, the box is padded
+/// by `TOLERANCE` for points which round to just outside of it.
+fn bounds(patch: &Patch) -> (na::Vector2<f32>, na::Vector2<f32>) {
+    let (x, y) = patch.bezier_net();
+
+    (
+        na::Vector2::new(x.min(), y.min()).add_scalar(-TOLERANCE),
+        na::Vector2::new(x.max(), y.max()).add_scalar(TOLERANCE),
+    )
+}
+
+/// Front-facing parameters of the patch mapped onto `target`, the last drawn one if there are several.
+fn visible_parameters(patch: &Patch, target: na::Vector2<f32>) -> Option<(f32, f32)> {
+    // Parts of the domain as `(u, v, size)`, starting with the whole patch.
+    let mut parts = vec![(0.0, 0.0, 1.0)];
+
+    for _ in 0..DEPTH {
+        parts = parts
+            .into_iter()
+            .flat_map(|(u, v, size)| {
+                let half = size / 2.0;
+                [(u, v), (u + half, v), (u, v + half), (u + half, v + half)]
+                    .map(|(u, v)| (u, v, half))
+            })
+            .filter(|&(u, v, size)| {
+                let (min, max) = bounds(&patch.part(u, v, size));
+
+                (min.x..=max.x).contains(&target.x) && (min.y..=max.y).contains(&target.y)
+            })
+            .collect();
+    }
+
+    parts
+        .into_iter()
+        .filter_map(|(u, v, size)| invert(patch, target, (u + size / 2.0, v + size / 2.0)))
+        .filter(|&(u, v)| {
+            let derivatives = patch.derivatives(u, v);
+            let (du, dv) = (derivatives.position_du, derivatives.position_dv);
+
+            dv.x * du.y - du.x * dv.y > 0.0
+        })
+        .max_by(|a, b| a.partial_cmp(b).unwrap())
+}
+
+/// Newton's method on `patch.point(u, v) = target` from `(u, v)`. Fails when it doesn't converge,
+/// converges outside of the patch or runs into a zero Jacobian.
+fn invert(
+    patch: &Patch,
+    target: na::Vector2<f32>,
+    (mut u, mut v): (f32, f32),
+) -> Option<(f32, f32)> {
+    for _ in 0..NEWTON_ITERATIONS {
+        let error = patch.point(u, v) - target;
+
+        if error.norm() < TOLERANCE {
+            // Solutions rounded to just outside of the patch are moved onto its edge.
+            let (u, v) = (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+
+            return ((patch.point(u, v) - target).norm() < TOLERANCE).then_some((u, v));
+        }
+
+        let derivatives = patch.derivatives(u, v);
+        let jacobian =
+            na::Matrix2::from_columns(&[derivatives.position_du, derivatives.position_dv]);
+        let step = jacobian.try_inverse()? * error;
+
+        u = (u - step.x).clamp(-MARGIN, 1.0 + MARGIN);
+        v = (v - step.y).clamp(-MARGIN, 1.0 + MARGIN);
+    }
+
+    None
+}
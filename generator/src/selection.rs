@@ -0,0 +1,148 @@
+use std::collections::BTreeSet;
+
+use nalgebra as na;
+
+use generator::Mesh;
+
+use crate::history::Edit;
+
+/// Set of selected control point indexes.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    points: BTreeSet<usize>,
+}
+
+impl Selection {
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    pub fn select(&mut self, idx: usize) {
+        self.points.clear();
+        self.points.insert(idx);
+    }
+
+    pub fn toggle(&mut self, idx: usize) {
+        if !self.points.remove(&idx) {
+            self.points.insert(idx);
+        }
+    }
+
+    pub fn extend(&mut self, idxs: impl IntoIterator<Item = usize>) {
+        self.points.extend(idxs);
+    }
+
+    pub fn contains(&self, idx: usize) -> bool {
+        self.points.contains(&idx)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.points.iter().copied()
+    }
+
+    /// The selected point, if exactly one is selected.
+    pub fn single(&self) -> Option<usize> {
+        match self.points.len() {
+            1 => self.points.first().copied(),
+            _ => None,
+        }
+    }
+
+    /// Drops indexes which no longer exist in the mesh, e.g. after undoing a grid change.
+    pub fn retain_valid(&mut self, mesh: &Mesh) {
+        self.points.retain(|&idx| idx < mesh.points.len());
+    }
+
+    pub fn centroid(&self, mesh: &Mesh) -> Option<na::Vector2<f32>> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let sum = self.iter().fold(na::Vector2::zeros(), |sum, idx| {
+            sum + mesh.points[idx].position
+        });
+
+        Some(sum / self.points.len() as f32)
+    }
+
+    /// Extends the selection to whole rows of the mesh containing any selected point.
+    pub fn grow_to_rows(&mut self, mesh: &Mesh) {
+        let rows: BTreeSet<usize> = self.iter().map(|idx| idx / mesh.width).collect();
+
+        for h in rows {
+            self.points
+                .extend((0..mesh.width).map(|w| h * mesh.width + w));
+        }
+    }
+
+    /// Extends the selection to whole columns of the mesh containing any selected point.
+    pub fn grow_to_columns(&mut self, mesh: &Mesh) {
+        let columns: BTreeSet<usize> = self.iter().map(|idx| idx % mesh.width).collect();
+
+        for w in columns {
+            self.points
+                .extend((0..mesh.height).map(|h| h * mesh.width + w));
+        }
+    }
+
+    /// Edit applying an affine transform `position -> m * (position - pivot) + pivot + offset` to the selected points.
+    /// Tangents and twists go through the linear part only, so the patches transform together with their corners.
+    pub fn transform_edit(
+        &self,
+        mesh: &Mesh,
+        m: &na::Matrix2<f32>,
+        pivot: na::Vector2<f32>,
+        offset: na::Vector2<f32>,
+    ) -> Edit {
+        let from: Vec<_> = self.iter().map(|idx| mesh.points[idx].clone()).collect();
+        let to = from
+            .iter()
+            .map(|point| {
+                let mut point = point.clone();
+                point.position = m * (point.position - pivot) + pivot + offset;
+                point.u_tangent = m * point.u_tangent;
+                point.v_tangent = m * point.v_tangent;
+                point.twist = m * point.twist;
+                point
+            })
+            .collect();
+
+        Edit::SetPoints {
+            idxs: self.iter().collect(),
+            from,
+            to,
+        }
+    }
+
+    /// Edit moving all selected points by `offset`, shortened so none of them leaves `bounds`.
+    pub fn translate_edit(
+        &self,
+        mesh: &Mesh,
+        offset: na::Vector2<f32>,
+        bounds: (na::Vector2<f32>, na::Vector2<f32>),
+    ) -> Edit {
+        let positions: Vec<_> = self.iter().map(|idx| mesh.points[idx].position).collect();
+        let min = positions
+            .iter()
+            .fold(na::Vector2::repeat(f32::INFINITY), |min, p| min.inf(p));
+        let max = positions
+            .iter()
+            .fold(na::Vector2::repeat(f32::NEG_INFINITY), |max, p| max.sup(p));
+
+        // Points already outside the bounds (e.g. after turning off the extended canvas)
+        // may still move back in, but not further out.
+        let offset = offset
+            .sup(&(bounds.0 - min).inf(&na::Vector2::zeros()))
+            .inf(&(bounds.1 - max).sup(&na::Vector2::zeros()));
+
+        self.transform_edit(mesh, &na::Matrix2::identity(), na::Vector2::zeros(), offset)
+    }
+}
@@ -0,0 +1,140 @@
+use nalgebra as na;
+
+use generator::Mesh;
+
+use crate::selection::Selection;
+
+/// Number of line segments each patch edge is approximated with when snapping to it.
+const EDGE_SEGMENTS: u32 = 50;
+
+pub struct Snapping {
+    pub show_grid: bool,
+    pub to_grid: bool,
+    /// Snap to the x / y coordinates of other points.
+    pub to_axes: bool,
+    /// Snap onto boundary curves of patches the dragged points don't belong to.
+    pub to_edges: bool,
+    pub grid_step: f32,
+}
+
+impl Default for Snapping {
+    fn default() -> Self {
+        Self {
+            show_grid: false,
+            to_grid: false,
+            to_axes: false,
+            to_edges: false,
+            grid_step: 0.1,
+        }
+    }
+}
+
+impl Snapping {
+    /// Snaps `target` - the new position of a point from `moving` - within `tolerance` (in mesh space).
+    /// Points being moved are never snapped to, since they move along.
+    ///
+    /// Patch edges win over everything else. Otherwise each coordinate is snapped separately,
+    /// to the nearest axis of another point if one is close enough, to the grid if not.
+    pub fn snap(
+        &self,
+        mesh: &Mesh,
+        moving: &Selection,
+        target: na::Vector2<f32>,
+        tolerance: f32,
+    ) -> na::Vector2<f32> {
+        if self.to_edges {
+            if let Some((on_edge, distance)) = nearest_on_patch_edges(mesh, moving, target) {
+                if distance < tolerance {
+                    return on_edge;
+                }
+            }
+        }
+
+        let mut snapped = target;
+
+        for coord in 0..2 {
+            let axis = self
+                .to_axes
+                .then(|| {
+                    mesh.points
+                        .iter()
+                        .enumerate()
+                        .filter(|(other_idx, _)| !moving.contains(*other_idx))
+                        .map(|(_, point)| point.position[coord])
+                        .filter(|value| (value - target[coord]).abs() < tolerance)
+                        .min_by(|a, b| {
+                            (a - target[coord])
+                                .abs()
+                                .total_cmp(&(b - target[coord]).abs())
+                        })
+                })
+                .flatten();
+
+            if let Some(axis) = axis {
+                snapped[coord] = axis;
+            } else if self.to_grid {
+                snapped[coord] = (target[coord] / self.grid_step).round() * self.grid_step;
+            }
+        }
+
+        snapped
+    }
+}
+
+/// Closest point to `target` on the boundary curves of patches which don't have a `moving` corner,
+/// since edges of those move together with the dragged points. Returns the point and its distance.
+fn nearest_on_patch_edges(
+    mesh: &Mesh,
+    moving: &Selection,
+    target: na::Vector2<f32>,
+) -> Option<(na::Vector2<f32>, f32)> {
+    let mut nearest: Option<(na::Vector2<f32>, f32)> = None;
+
+    for w in 0..mesh.width - 1 {
+        for h in 0..mesh.height - 1 {
+            let corners = [(w, h), (w, h + 1), (w + 1, h), (w + 1, h + 1)];
+
+            if corners
+                .iter()
+                .any(|&(w, h)| moving.contains(h * mesh.width + w))
+            {
+                continue;
+            }
+
+            let patch = mesh.patch(w, h);
+
+            let edges: [fn(f32) -> (f32, f32); 4] =
+                [|t| (t, 0.0), |t| (t, 1.0), |t| (0.0, t), |t| (1.0, t)];
+
+            for edge in edges {
+                let at = |i: u32| {
+                    let (u, v) = edge(i as f32 / EDGE_SEGMENTS as f32);
+                    patch.point(u, v)
+                };
+
+                let mut start = at(0);
+
+                for i in 1..=EDGE_SEGMENTS {
+                    let end = at(i);
+                    let segment = end - start;
+                    let t = if segment.norm_squared() > 0.0 {
+                        ((target - start).dot(&segment) / segment.norm_squared()).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+
+                    let candidate = start + segment * t;
+                    let distance = (candidate - target).norm();
+
+                    if nearest.map_or(true, |(_, best)| distance < best) {
+                        nearest = Some((candidate, distance));
+                    }
+
+                    start = end;
+                }
+            }
+        }
+    }
+
+    nearest
+}
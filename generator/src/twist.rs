@@ -0,0 +1,58 @@
+use nalgebra as na;
+
+use crate::patch::PatchKind;
+use crate::Mesh;
+
+impl Mesh {
+    /// Sets every twist to Adini's estimate - the twist of the bilinearly blended Coons surface
+    /// through the patch edges. It is exact for bilinear surfaces and removes the flat spots
+    /// zero twists leave in sheared or bent grids.
+    ///
+    /// Colors have no tangents, so their twist is the cross difference of the neighbouring
+    /// colors instead. Differences are one-sided at the edges of the grid.
+    pub fn estimate_twists(&mut self) {
+        // Estimates are made on Hermite derivatives.
+        let (tangent_scale, twist_scale) = match self.kind {
+            PatchKind::Bezier => (3.0, 1.0 / 9.0),
+            _ => (1.0, 1.0),
+        };
+
+        let twists: Vec<_> = (0..self.height)
+            .flat_map(|h| (0..self.width).map(move |w| (w, h)))
+            .map(|(w, h)| {
+                let (w0, w1) = (w.saturating_sub(1), (w + 1).min(self.width - 1));
+                let (h0, h1) = (h.saturating_sub(1), (h + 1).min(self.height - 1));
+                let (dw, dh) = ((w1 - w0) as f32, (h1 - h0) as f32);
+
+                let cross = |value: &dyn Fn(usize, usize) -> na::Vector3<f32>| {
+                    (value(w1, h1) - value(w1, h0) - value(w0, h1) + value(w0, h0)) / (dw * dh)
+                };
+
+                let position = |w, h| self.point_at(w, h).position.push(0.0);
+                let u_tangent = |w, h| self.point_at(w, h).u_tangent.push(0.0);
+                let v_tangent = |w, h| self.point_at(w, h).v_tangent.push(0.0);
+
+                let twist = ((u_tangent(w, h1) - u_tangent(w, h0)) / dh
+                    + (v_tangent(w1, h) - v_tangent(w0, h)) / dw)
+                    * tangent_scale
+                    - cross(&position);
+
+                let color_twist = cross(&|w, h| self.point_at(w, h).color);
+
+                (twist.xy() * twist_scale, color_twist)
+            })
+            .collect();
+
+        for (point, (twist, color_twist)) in self.points.iter_mut().zip(twists) {
+            point.twist = twist;
+            point.color_twist = color_twist;
+        }
+    }
+
+    pub fn clear_twists(&mut self) {
+        for point in &mut self.points {
+            point.twist = na::Vector2::zeros();
+            point.color_twist = na::Vector3::zeros();
+        }
+    }
+}
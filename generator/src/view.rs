@@ -0,0 +1,87 @@
+use nalgebra as na;
+
+/// Space left around the unit square at the default zoom, in pixels.
+const PADDING: f32 = 160.0;
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 50.0;
+
+/// Mapping between mesh space and the workspace part of the window.
+///
+/// At the default zoom the unit square is centered in the workspace and fitted into it
+/// with some padding, stretched to the design aspect ratio. Zoom scales around that,
+/// pan shifts the result by screen pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    /// Workspace size in pixels, its top left corner is at the origin of the window.
+    size: na::Vector2<f32>,
+    /// Width / height ratio the unit square is shown at.
+    aspect: f32,
+    zoom: f32,
+    pan: na::Vector2<f32>,
+}
+
+impl View {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            size: na::Vector2::new(width, height),
+            aspect: 1.0,
+            zoom: 1.0,
+            pan: na::Vector2::zeros(),
+        }
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.size = na::Vector2::new(width, height);
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
+
+    pub fn reset(&mut self) {
+        self.zoom = 1.0;
+        self.pan = na::Vector2::zeros();
+    }
+
+    pub fn size(&self) -> na::Vector2<f32> {
+        self.size
+    }
+
+    pub fn contains(&self, screen: na::Vector2<f32>) -> bool {
+        screen.x >= 0.0 && screen.y >= 0.0 && screen.x < self.size.x && screen.y < self.size.y
+    }
+
+    /// Pixels per mesh space unit along x and y.
+    pub fn scale(&self) -> na::Vector2<f32> {
+        let height = ((self.size.x - PADDING) / self.aspect).min(self.size.y - PADDING);
+        let height = height.max(1.0) * self.zoom;
+
+        na::Vector2::new(height * self.aspect, height)
+    }
+
+    fn origin(&self) -> na::Vector2<f32> {
+        (self.size - self.scale()) / 2.0 + self.pan
+    }
+
+    /// Maps a mesh space position to the screen.
+    pub fn ws_coord(&self, point: &na::Vector2<f32>) -> na::Vector2<f32> {
+        point.component_mul(&self.scale()) + self.origin()
+    }
+
+    /// Inverse of `ws_coord` - maps a screen position to mesh space.
+    pub fn pt_coord(&self, point: &na::Vector2<f32>) -> na::Vector2<f32> {
+        (point - self.origin()).component_div(&self.scale())
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the mesh space point under `cursor` in place.
+    pub fn zoom_at(&mut self, cursor: na::Vector2<f32>, factor: f32) {
+        let anchor = self.pt_coord(&cursor);
+
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan += cursor - self.ws_coord(&anchor);
+    }
+
+    pub fn pan_by(&mut self, delta: na::Vector2<f32>) {
+        self.pan += delta;
+    }
+}
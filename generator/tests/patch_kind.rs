@@ -0,0 +1,27 @@
+use nalgebra as na;
+
+use generator::patch::{bezier_to_hermite, hermite_to_bezier};
+
+/// Arbitrary coefficients without any structure the conversions could depend on.
+fn matrix() -> na::Matrix4<f32> {
+    na::Matrix4::from_fn(|row, col| ((row * 4 + col) as f32 * 1.7).sin())
+}
+
+#[test]
+fn hermite_and_bezier_conversions_round_trip() {
+    let matrix = matrix();
+
+    assert!((bezier_to_hermite(&hermite_to_bezier(&matrix)) - matrix).amax() < 1e-5);
+    assert!((hermite_to_bezier(&bezier_to_hermite(&matrix)) - matrix).amax() < 1e-5);
+}
+
+#[test]
+fn straight_hermite_patches_get_evenly_spaced_bezier_nets() {
+    // `[p0, p1, t0, t1]` of `t` itself, in both directions.
+    let hermite = na::Vector4::new(0.0, 1.0, 1.0, 1.0);
+    let bezier = na::Vector4::new(0.0, 1.0 / 3.0, 2.0 / 3.0, 1.0);
+
+    let net = hermite_to_bezier(&(hermite * hermite.transpose()));
+
+    assert!((net - bezier * bezier.transpose()).amax() < 1e-6);
+}
@@ -0,0 +1,518 @@
+use nalgebra as na;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use generator::patch::PatchKind;
+use generator::random::{random_mesh, Harmony, Seed};
+use std::sync::atomic::AtomicUsize;
+
+use generator::{
+    construct_mesh, construct_mesh_with_attributes, Attributes, ControlPoint, Derivatives, Mesh,
+    MAX_SUBDIVS,
+};
+
+const TOLERANCE: f32 = 1e-4;
+
+fn vector2() -> impl Strategy<Value = na::Vector2<f32>> {
+    (-1.0f32..1.0, -1.0f32..1.0).prop_map(|(x, y)| na::Vector2::new(x, y))
+}
+
+fn vector3() -> impl Strategy<Value = na::Vector3<f32>> {
+    (0.0f32..1.0, 0.0f32..1.0, 0.0f32..1.0).prop_map(|(r, g, b)| na::Vector3::new(r, g, b))
+}
+
+fn control_point() -> impl Strategy<Value = ControlPoint> {
+    (
+        (vector2(), vector2(), vector2()),
+        (vector3(), vector2(), vector3()),
+    )
+        .prop_map(
+            |((position, u_tangent, v_tangent), (color, twist, color_twist))| {
+                let mut point = ControlPoint::new(position, color, 2, 2);
+                point.u_tangent = u_tangent;
+                point.v_tangent = v_tangent;
+                point.twist = twist;
+                point.color_twist = color_twist;
+                point
+            },
+        )
+}
+
+/// Meshes with arbitrary control points, which may well fold over.
+fn mesh(kinds: &'static [PatchKind]) -> impl Strategy<Value = Mesh> {
+    (2usize..6, 2usize..6, proptest::sample::select(kinds)).prop_flat_map(
+        |(width, height, kind)| {
+            vec(control_point(), width * height).prop_map(move |points| Mesh {
+                width,
+                height,
+                points,
+                kind,
+            })
+        },
+    )
+}
+
+/// Output of `random_mesh`, which promises meshes that neither fold nor flip a triangle.
+fn random_meshes() -> impl Strategy<Value = Mesh> {
+    seeds().prop_map(|seed| seed.mesh())
+}
+
+fn seeds() -> impl Strategy<Value = Seed> {
+    (
+        2usize..7,
+        2usize..7,
+        proptest::sample::select(
+            &[
+                Harmony::Analogous,
+                Harmony::Complementary,
+                Harmony::Triadic,
+                Harmony::Palette,
+            ][..],
+        ),
+        "[a-z0-9-]{1,8}",
+    )
+        .prop_map(|(width, height, harmony, text)| Seed {
+            width,
+            height,
+            harmony,
+            text,
+        })
+}
+
+/// Regular grids with interior points moved by at most a fifth of a cell, in every patch kind.
+/// Their patches stay well away from folding.
+fn jittered_mesh() -> impl Strategy<Value = Mesh> {
+    (
+        2usize..6,
+        2usize..6,
+        proptest::sample::select(
+            &[
+                PatchKind::Hermite,
+                PatchKind::Bezier,
+                PatchKind::CatmullRom,
+                PatchKind::BSpline,
+            ][..],
+        ),
+    )
+        .prop_flat_map(|(width, height, kind)| {
+            vec((-0.2f32..0.2, -0.2f32..0.2), width * height).prop_map(move |offsets| {
+                let mut mesh = Mesh::new(width, height, vec![na::Vector3::zeros(); width * height]);
+
+                for h in 1..height - 1 {
+                    for w in 1..width - 1 {
+                        let (x, y) = offsets[h * width + w];
+                        mesh.points[h * width + w].position +=
+                            na::Vector2::new(x / (width - 1) as f32, y / (height - 1) as f32);
+                    }
+                }
+
+                mesh.with_kind(kind)
+            })
+        })
+}
+
+fn close2(a: na::Vector2<f32>, b: na::Vector2<f32>) -> bool {
+    (a - b).abs().max() < TOLERANCE
+}
+
+fn close3(a: na::Vector3<f32>, b: na::Vector3<f32>) -> bool {
+    (a - b).abs().max() < TOLERANCE
+}
+
+/// Every triangle is counter-clockwise in normalized device coordinates, so none gets culled.
+fn assert_ccw(positions: &[na::Vector3<f32>], indexes: &[u32]) {
+    for triangle in indexes.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i] as usize].xy());
+        let area = (b - a).perp(&(c - a));
+
+        assert!(area > 0.0, "triangle {triangle:?} has area {area}");
+    }
+}
+
+proptest! {
+    #[test]
+    fn patches_interpolate_their_corners(
+        mesh in mesh(&[PatchKind::Hermite, PatchKind::Bezier, PatchKind::CatmullRom]),
+    ) {
+        for h in 0..mesh.height - 1 {
+            for w in 0..mesh.width - 1 {
+                let patch = mesh.patch(w, h);
+
+                for (u, v, corner) in [
+                    (0.0, 0.0, mesh.point_at(w, h)),
+                    (0.0, 1.0, mesh.point_at(w + 1, h)),
+                    (1.0, 0.0, mesh.point_at(w, h + 1)),
+                    (1.0, 1.0, mesh.point_at(w + 1, h + 1)),
+                ] {
+                    prop_assert!(close2(patch.point(u, v), corner.position));
+                    prop_assert!(close3(patch.color(u, v), corner.color));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neighbouring_patches_share_edges(
+        mesh in mesh(&[PatchKind::Hermite, PatchKind::Bezier, PatchKind::CatmullRom, PatchKind::BSpline]),
+        t in 0.0f32..1.0,
+    ) {
+        for h in 0..mesh.height - 1 {
+            for w in 0..mesh.width - 1 {
+                let patch = mesh.patch(w, h);
+
+                if w + 2 < mesh.width {
+                    let right = mesh.patch(w + 1, h);
+                    prop_assert!(close2(patch.point(t, 1.0), right.point(t, 0.0)));
+                    prop_assert!(close3(patch.color(t, 1.0), right.color(t, 0.0)));
+                }
+
+                if h + 2 < mesh.height {
+                    let below = mesh.patch(w, h + 1);
+                    prop_assert!(close2(patch.point(1.0, t), below.point(0.0, t)));
+                    prop_assert!(close3(patch.color(1.0, t), below.color(0.0, t)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn corner_derivatives_match_tangents(mesh in mesh(&[PatchKind::Hermite, PatchKind::Bezier])) {
+        // Bezier tangents are handle offsets, a third of the derivative.
+        let scale = match mesh.kind {
+            PatchKind::Bezier => 3.0,
+            _ => 1.0,
+        };
+
+        for h in 0..mesh.height - 1 {
+            for w in 0..mesh.width - 1 {
+                let patch = mesh.patch(w, h);
+
+                for (u, v, corner) in [
+                    (0.0, 0.0, mesh.point_at(w, h)),
+                    (0.0, 1.0, mesh.point_at(w + 1, h)),
+                    (1.0, 0.0, mesh.point_at(w, h + 1)),
+                    (1.0, 1.0, mesh.point_at(w + 1, h + 1)),
+                ] {
+                    let derivatives = patch.derivatives(u, v);
+                    prop_assert!(close2(derivatives.position_dv, corner.u_tangent * scale));
+                    prop_assert!(close2(derivatives.position_du, corner.v_tangent * scale));
+                    prop_assert!(close3(derivatives.color_du, na::Vector3::zeros()));
+                    prop_assert!(close3(derivatives.color_dv, na::Vector3::zeros()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences(
+        mesh in mesh(&[PatchKind::Hermite, PatchKind::Bezier, PatchKind::CatmullRom, PatchKind::BSpline]),
+        u in 0.01f32..0.99,
+        v in 0.01f32..0.99,
+    ) {
+        const STEP: f32 = 1e-3;
+
+        let patch = mesh.patch(0, 0);
+        let derivatives = patch.derivatives(u, v);
+
+        let position_du = (patch.point(u + STEP, v) - patch.point(u - STEP, v)) / (2.0 * STEP);
+        let position_dv = (patch.point(u, v + STEP) - patch.point(u, v - STEP)) / (2.0 * STEP);
+        let color_du = (patch.color(u + STEP, v) - patch.color(u - STEP, v)) / (2.0 * STEP);
+        let color_dv = (patch.color(u, v + STEP) - patch.color(u, v - STEP)) / (2.0 * STEP);
+
+        // Differences of f32 values are much less precise than the analytic derivatives.
+        let close = |a: f32, b: f32| (a - b).abs() < 0.02 * (1.0 + b.abs());
+
+        prop_assert!(position_du.iter().zip(&derivatives.position_du).all(|(a, b)| close(*a, *b)));
+        prop_assert!(position_dv.iter().zip(&derivatives.position_dv).all(|(a, b)| close(*a, *b)));
+        prop_assert!(color_du.iter().zip(&derivatives.color_du).all(|(a, b)| close(*a, *b)));
+        prop_assert!(color_dv.iter().zip(&derivatives.color_dv).all(|(a, b)| close(*a, *b)));
+    }
+
+    #[test]
+    fn batched_evaluation_matches_single_points(
+        mesh in mesh(&[PatchKind::Hermite, PatchKind::Bezier, PatchKind::CatmullRom, PatchKind::BSpline]),
+        us in vec(0.0f32..1.0, 1..5),
+        vs in vec(0.0f32..1.0, 1..5),
+    ) {
+        let patch = mesh.patch(0, 0);
+        let (points, colors) = patch.evaluate_grid(&us, &vs);
+        let derivatives = patch.derivative_grid(&us, &vs);
+
+        let pairs = us.iter().flat_map(|&u| vs.iter().map(move |&v| (u, v)));
+
+        for (i, (u, v)) in pairs.enumerate() {
+            let expected = patch.derivatives(u, v);
+
+            prop_assert!(close2(points[i], patch.point(u, v)));
+            prop_assert!(close3(colors[i], patch.color(u, v)));
+            prop_assert!(close2(derivatives[i].position_du, expected.position_du));
+            prop_assert!(close2(derivatives[i].position_dv, expected.position_dv));
+            prop_assert!(close3(derivatives[i].color_du, expected.color_du));
+            prop_assert!(close3(derivatives[i].color_dv, expected.color_dv));
+        }
+    }
+
+    #[test]
+    fn parts_follow_the_patch(
+        mesh in mesh(&[PatchKind::Hermite, PatchKind::Bezier, PatchKind::CatmullRom, PatchKind::BSpline]),
+        size in 0.01f32..1.0,
+        start in (0.0f32..1.0, 0.0f32..1.0),
+        s in 0.0f32..1.0,
+        t in 0.0f32..1.0,
+    ) {
+        let (u, v) = (start.0 * (1.0 - size), start.1 * (1.0 - size));
+        let patch = mesh.patch(0, 0);
+        let part = patch.part(u, v, size);
+
+        prop_assert!(close2(part.point(s, t), patch.point(u + s * size, v + t * size)));
+        prop_assert!(close3(part.color(s, t), patch.color(u + s * size, v + t * size)));
+    }
+
+    #[test]
+    fn bezier_nets_evaluate_to_the_patch(
+        mesh in mesh(&[PatchKind::Hermite, PatchKind::Bezier, PatchKind::CatmullRom, PatchKind::BSpline]),
+        u in 0.0f32..1.0,
+        v in 0.0f32..1.0,
+    ) {
+        let bernstein = |t: f32| {
+            na::Vector4::new((1.0 - t).powi(3), 3.0 * t * (1.0 - t).powi(2), 3.0 * t * t * (1.0 - t), t.powi(3))
+        };
+
+        let patch = mesh.patch(0, 0);
+        let (x, y) = patch.bezier_net();
+        let point = na::Vector2::new(
+            (x * bernstein(v)).dot(&bernstein(u)),
+            (y * bernstein(v)).dot(&bernstein(u)),
+        );
+
+        prop_assert!(close2(point, patch.point(u, v)));
+    }
+
+    #[test]
+    fn vertex_attributes_cover_every_vertex(
+        mesh in mesh(&[PatchKind::Hermite, PatchKind::CatmullRom]),
+        subdivs in 0usize..4,
+    ) {
+        let ((positions, _, _), extra) =
+            construct_mesh_with_attributes(&mesh, subdivs, Attributes::ALL, &AtomicUsize::new(0));
+
+        prop_assert_eq!(extra.uvs.len(), positions.len());
+        prop_assert_eq!(extra.patch_ids.len(), positions.len());
+        prop_assert_eq!(extra.position_du.len(), positions.len());
+        prop_assert_eq!(extra.position_dv.len(), positions.len());
+        prop_assert_eq!(extra.color_du.len(), positions.len());
+        prop_assert_eq!(extra.color_dv.len(), positions.len());
+
+        let patches = (mesh.width - 1) * (mesh.height - 1);
+        prop_assert!(extra.patch_ids.iter().all(|&id| (id as usize) < patches));
+
+        for (i, uv) in extra.uvs.iter().enumerate() {
+            let id = extra.patch_ids[i] as usize;
+            let (w, h) = (id / (mesh.height - 1), id % (mesh.height - 1));
+            let point = mesh.patch(w, h).point(uv.x, uv.y);
+
+            prop_assert!((point.x * 2.0 - 1.0 - positions[i].x).abs() < TOLERANCE);
+            prop_assert!((1.0 - point.y * 2.0 - positions[i].y).abs() < TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn construct_mesh_indexes_are_in_range(
+        mesh in mesh(&[PatchKind::Hermite, PatchKind::Bezier, PatchKind::CatmullRom, PatchKind::BSpline]),
+        subdivs in 0usize..6,
+    ) {
+        let (positions, colors, indexes) = construct_mesh(&mesh, subdivs);
+
+        let patches = (mesh.width - 1) * (mesh.height - 1);
+        let steps = subdivs + 1;
+
+        prop_assert_eq!(positions.len(), patches * (steps + 1) * (steps + 1));
+        prop_assert_eq!(colors.len(), positions.len());
+        prop_assert_eq!(indexes.len(), patches * steps * steps * 6);
+        prop_assert!(indexes.iter().all(|&idx| (idx as usize) < positions.len()));
+    }
+
+    #[test]
+    fn sampling_finds_points_of_the_surface(
+        mesh in jittered_mesh(),
+        patch in any::<proptest::sample::Index>(),
+        u in 0.0f32..1.0,
+        v in 0.0f32..1.0,
+    ) {
+        let patch = patch.index((mesh.width - 1) * (mesh.height - 1));
+        let (w, h) = (patch / (mesh.height - 1), patch % (mesh.height - 1));
+        let target = mesh.patch(w, h).point(u, v);
+
+        // Points on a shared edge may come from the neighbouring patch, which has the same color there.
+        let sample = mesh.sample(target.x, target.y);
+        prop_assert!(sample.is_some(), "nothing at {:?}", target);
+
+        let sample = sample.unwrap();
+        let (sample_w, sample_h) = sample.patch;
+        let found = mesh.patch(sample_w, sample_h);
+
+        prop_assert!(close2(found.point(sample.u, sample.v), target));
+        prop_assert!((sample.color - mesh.patch(w, h).color(u, v)).abs().max() < 1e-3);
+    }
+
+    #[test]
+    fn sampling_folded_meshes_gives_front_faces(
+        mesh in mesh(&[PatchKind::Hermite, PatchKind::Bezier, PatchKind::CatmullRom, PatchKind::BSpline]),
+        u in 0.0f32..1.0,
+        v in 0.0f32..1.0,
+    ) {
+        let patch = mesh.patch(0, 0);
+        let target = patch.point(u, v);
+        let jacobian = |d: Derivatives| d.position_dv.perp(&d.position_du);
+
+        if let Some(sample) = mesh.sample(target.x, target.y) {
+            let (w, h) = sample.patch;
+            let found = mesh.patch(w, h);
+
+            prop_assert!(close2(found.point(sample.u, sample.v), target));
+            prop_assert!(jacobian(found.derivatives(sample.u, sample.v)) > 0.0);
+        } else {
+            // A front face of patch (0, 0) covers the location, unless it's right next to a crease.
+            const CREASE: f32 = 0.01;
+
+            let back_facing = |(u, v): (f32, f32)| {
+                jacobian(patch.derivatives(u.clamp(0.0, 1.0), v.clamp(0.0, 1.0))) <= 0.0
+            };
+            let near_crease = [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)]
+                .iter()
+                .any(|(du, dv)| back_facing((u + du * CREASE, v + dv * CREASE)));
+
+            prop_assert!(back_facing((u, v)) || near_crease, "missed {:?}", target);
+        }
+    }
+
+    #[test]
+    fn sampling_outside_the_mesh_gives_none(
+        mesh in jittered_mesh(),
+        x in 1.01f32..2.0,
+        y in -1.0f32..2.0,
+    ) {
+        prop_assert!(mesh.sample(x, y).is_none());
+        prop_assert!(mesh.sample(1.0 - x, y).is_none());
+        prop_assert!(mesh.sample(y, x).is_none());
+        prop_assert!(mesh.sample(y, 1.0 - x).is_none());
+    }
+
+    #[test]
+    fn construct_mesh_winding_is_consistent_in_every_kind(
+        mesh in jittered_mesh(),
+        subdivs in 0..=MAX_SUBDIVS,
+    ) {
+        let (positions, _, indexes) = construct_mesh(&mesh, subdivs);
+        assert_ccw(&positions, &indexes);
+    }
+
+    #[test]
+    fn splines_of_regular_grids_become_hermite_without_twists(
+        (width, height, colors) in (2usize..6, 2usize..6).prop_flat_map(|(width, height)| {
+            (Just(width), Just(height), vec(vector3(), width * height))
+        }),
+        kind in proptest::sample::select(&[PatchKind::CatmullRom, PatchKind::BSpline][..]),
+        u in 0.0f32..1.0,
+        v in 0.0f32..1.0,
+    ) {
+        let spline = Mesh { kind, ..Mesh::new(width, height, colors) };
+        let hermite = spline.with_kind(PatchKind::Hermite);
+
+        prop_assert!(hermite.points.iter().all(|point| close2(point.twist, na::Vector2::zeros())));
+
+        for h in 0..height - 1 {
+            for w in 0..width - 1 {
+                prop_assert!(close2(hermite.patch(w, h).point(u, v), spline.patch(w, h).point(u, v)));
+            }
+        }
+    }
+
+    #[test]
+    fn catmull_rom_keeps_its_surface_in_hermite_and_bezier(
+        mesh in mesh(&[PatchKind::CatmullRom]),
+        kind in proptest::sample::select(&[PatchKind::Hermite, PatchKind::Bezier][..]),
+        u in 0.0f32..1.0,
+        v in 0.0f32..1.0,
+    ) {
+        let converted = mesh.with_kind(kind);
+
+        for h in 0..mesh.height - 1 {
+            for w in 0..mesh.width - 1 {
+                prop_assert!(close2(converted.patch(w, h).point(u, v), mesh.patch(w, h).point(u, v)));
+            }
+        }
+    }
+
+    #[test]
+    fn estimated_twists_of_regular_grids_are_zero(
+        (width, height, colors) in (2usize..6, 2usize..6).prop_flat_map(|(width, height)| {
+            (Just(width), Just(height), vec(vector3(), width * height))
+        }),
+        kind in proptest::sample::select(&[PatchKind::Hermite, PatchKind::Bezier][..]),
+    ) {
+        let mut mesh = Mesh::new(width, height, colors).with_kind(kind);
+        mesh.estimate_twists();
+
+        prop_assert!(mesh.points.iter().all(|point| close2(point.twist, na::Vector2::zeros())));
+    }
+}
+
+proptest! {
+    // Fine tessellations of the bigger meshes are slow in debug builds.
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn construct_mesh_winding_is_consistent(
+        mesh in random_meshes(),
+        subdivs in 0..=MAX_SUBDIVS,
+    ) {
+        let (positions, _, indexes) = construct_mesh(&mesh, subdivs);
+        assert_ccw(&positions, &indexes);
+    }
+}
+
+/// Both were once jittered right next to a cusp - no patch folded, but a triangle of a coarse
+/// tessellation flipped and the visualizer culled it.
+#[test]
+fn random_meshes_near_a_cusp_keep_their_winding() {
+    for size in [5, 8] {
+        let mesh = random_mesh("r", size, size, &Harmony::Analogous);
+
+        for subdivs in 0..=MAX_SUBDIVS {
+            let (positions, _, indexes) = construct_mesh(&mesh, subdivs);
+            assert_ccw(&positions, &indexes);
+        }
+    }
+}
+
+proptest! {
+    // Every case generates two meshes, checking each candidate for folds.
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn full_seeds_reproduce_their_mesh(seed in seeds()) {
+        let parsed = Seed::parse(&seed.to_string());
+        prop_assert_eq!(parsed.as_ref(), Some(&seed));
+
+        let (a, b) = (seed.mesh(), parsed.unwrap().mesh());
+        for (a, b) in a.points.iter().zip(&b.points) {
+            prop_assert_eq!(a.position, b.position);
+            prop_assert_eq!(a.color, b.color);
+        }
+    }
+}
+
+#[test]
+fn plain_text_is_not_a_full_seed() {
+    for text in [
+        "",
+        "sunset",
+        "3x3",
+        "3x3-triadic",
+        "1x3-triadic-a",
+        "3x3-pastel-a",
+    ] {
+        assert_eq!(Seed::parse(text), None, "{text}");
+    }
+}
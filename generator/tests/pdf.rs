@@ -0,0 +1,44 @@
+use generator::pdf::pdf;
+use generator::preset;
+
+/// Byte offset of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[test]
+fn cross_reference_table_points_at_objects() {
+    let mesh = preset::builtin().remove(0).mesh;
+    let pdf = pdf(&mesh, 2);
+
+    let text = String::from_utf8_lossy(&pdf);
+    let start_xref = text.rfind("startxref\n").unwrap() + "startxref\n".len();
+    let xref: usize = text[start_xref..].lines().next().unwrap().parse().unwrap();
+
+    assert!(pdf[xref..].starts_with(b"xref\n0 6\n"));
+
+    // Entries after the free one, each 20 bytes.
+    let entries = &pdf[xref + "xref\n0 6\n".len() + 20..];
+    for (i, entry) in entries.chunks(20).take(5).enumerate() {
+        let offset: usize = std::str::from_utf8(&entry[..10]).unwrap().parse().unwrap();
+        assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()));
+    }
+}
+
+#[test]
+fn shading_has_one_record_per_patch() {
+    let mesh = preset::builtin().remove(0).mesh;
+    let subdivs = 3;
+    let pdf = pdf(&mesh, subdivs);
+
+    let patches = (mesh.width - 1) * (mesh.height - 1) * (subdivs + 1) * (subdivs + 1);
+    // A flag byte, 16 points of two 32 bit coordinates and 4 colors of three 16 bit components.
+    let length = patches * (1 + 16 * 8 + 4 * 6);
+
+    let shading = find(&pdf, b"/ShadingType 7").unwrap();
+    let dictionary = &pdf[shading..shading + find(&pdf[shading..], b">>").unwrap()];
+
+    assert!(find(dictionary, format!("/Length {length}").as_bytes()).is_some());
+}
@@ -0,0 +1,110 @@
+use nalgebra as na;
+
+use generator::reference::{ReferenceImage, Underlay};
+use generator::Mesh;
+
+const TOLERANCE: f32 = 1e-5;
+
+/// `width x height` image, black except for a white pixel at `(x, y)`.
+fn dot(width: usize, height: usize, x: usize, y: usize) -> ReferenceImage {
+    let mut pixels = vec![na::Vector3::zeros(); width * height];
+    pixels[y * width + x] = na::Vector3::repeat(1.0);
+
+    ReferenceImage {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// Position of the center of pixel `(x, y)` in image space.
+fn center(image: &ReferenceImage, x: usize, y: usize) -> na::Vector2<f32> {
+    na::Vector2::new(
+        (x as f32 + 0.5) / image.width as f32,
+        (y as f32 + 0.5) / image.height as f32,
+    )
+}
+
+#[test]
+fn pixel_centers_give_their_own_color() {
+    let image = dot(4, 3, 2, 1);
+
+    assert_eq!(image.color_at(center(&image, 2, 1), 0.0).x, 1.0);
+    assert_eq!(image.color_at(center(&image, 1, 1), 0.0).x, 0.0);
+}
+
+#[test]
+fn colors_between_pixels_are_interpolated() {
+    let image = dot(4, 3, 2, 1);
+    let between = (center(&image, 1, 1) + center(&image, 2, 1)) / 2.0;
+    let quarter = center(&image, 2, 1) * 0.75 + center(&image, 2, 2) * 0.25;
+
+    assert!((image.color_at(between, 0.0).x - 0.5).abs() < TOLERANCE);
+    assert!((image.color_at(quarter, 0.0).x - 0.75).abs() < TOLERANCE);
+}
+
+#[test]
+fn positions_outside_get_the_nearest_edge() {
+    let image = dot(3, 3, 2, 0);
+
+    assert_eq!(image.color_at(na::Vector2::new(5.0, -2.0), 0.0).x, 1.0);
+    assert_eq!(image.color_at(na::Vector2::new(5.0, 0.5), 0.0).x, 0.0);
+}
+
+#[test]
+fn blur_averages_the_pixels_within_its_radius() {
+    let image = dot(7, 7, 3, 3);
+
+    // The center and its four direct neighbours.
+    assert!((image.color_at(center(&image, 3, 3), 1.0).x - 1.0 / 5.0).abs() < TOLERANCE);
+    // 21 pixels with x^2 + y^2 <= 5.
+    assert!((image.color_at(center(&image, 3, 3), 2.3).x - 1.0 / 21.0).abs() < TOLERANCE);
+    // Out of reach of the dot.
+    assert_eq!(image.color_at(center(&image, 1, 3), 1.0).x, 0.0);
+    // Radii below half a pixel don't blur.
+    assert_eq!(image.color_at(center(&image, 3, 3), 0.4).x, 1.0);
+}
+
+#[test]
+fn underlay_maps_its_image_rect_onto_the_unit_square() {
+    let image = dot(4, 2, 0, 0);
+    let underlay = Underlay {
+        scale: 0.8,
+        offset: na::Vector2::new(0.1, -0.2),
+        ..Underlay::new(String::new())
+    };
+
+    for aspect in [0.5, 1.0, 3.0] {
+        let (origin, size) = underlay.image_rect(&image, aspect);
+
+        // The image keeps its ratio on a canvas stretched by `aspect`.
+        assert!((size.x * aspect / size.y - 2.0).abs() < TOLERANCE);
+        assert!(size.max() <= underlay.scale + TOLERANCE);
+
+        for corner in [na::Vector2::new(0.0, 0.0), na::Vector2::new(1.0, 1.0)] {
+            let position = origin + size.component_mul(&corner);
+            let mapped = underlay.to_image(&image, aspect, position);
+            assert!((mapped - corner).norm() < TOLERANCE, "{aspect}: {mapped}");
+        }
+    }
+}
+
+#[test]
+fn sampled_colors_follow_the_control_points() {
+    let image = dot(2, 2, 1, 0);
+    let underlay = Underlay::new(String::new());
+    let mut mesh = Mesh::new(2, 2, vec![na::Vector3::zeros(); 4]);
+
+    // Top right corner, then moved onto the dark bottom left pixel.
+    let idx = mesh
+        .points
+        .iter()
+        .position(|point| point.position == na::Vector2::new(1.0, 0.0))
+        .unwrap();
+    assert_eq!(image.sample_colors(&mesh, &underlay, 1.0, 0.0)[idx].x, 1.0);
+
+    mesh.points[idx].position = na::Vector2::new(0.25, 0.75);
+    let colors = image.sample_colors(&mesh, &underlay, 1.0, 0.0);
+    assert_eq!(colors.len(), mesh.points.len());
+    assert_eq!(colors[idx].x, 0.0);
+}
@@ -0,0 +1,101 @@
+/// GPU side of the camera: maps mesh NDC positions to clip space with
+/// `clip = pos * scale + offset`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    scale: [f32; 2],
+    offset: [f32; 2],
+}
+
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 100.0;
+
+pub struct Camera {
+    zoom: f32,
+    pan: [f32; 2],
+    /// Width / height ratio the mesh was designed for.
+    design_aspect: f32,
+    /// Letterbox the mesh to `design_aspect` instead of stretching it over the window.
+    pub keep_aspect: bool,
+    viewport: [f32; 2],
+}
+
+impl Camera {
+    pub fn new(design_aspect: f32, viewport_w: u32, viewport_h: u32) -> Self {
+        Self {
+            zoom: 1.0,
+            pan: [0.0, 0.0],
+            design_aspect,
+            keep_aspect: false,
+            viewport: [viewport_w.max(1) as f32, viewport_h.max(1) as f32],
+        }
+    }
+
+    pub fn resize(&mut self, viewport_w: u32, viewport_h: u32) {
+        self.viewport = [viewport_w.max(1) as f32, viewport_h.max(1) as f32];
+    }
+
+    pub fn reset(&mut self) {
+        self.zoom = 1.0;
+        self.pan = [0.0, 0.0];
+    }
+
+    fn fit(&self) -> [f32; 2] {
+        if !self.keep_aspect {
+            return [1.0, 1.0];
+        }
+
+        let window_aspect = self.viewport[0] / self.viewport[1];
+
+        if window_aspect > self.design_aspect {
+            // Window is wider than the design - bars on the left and right.
+            [self.design_aspect / window_aspect, 1.0]
+        } else {
+            // Window is taller than the design - bars on the top and bottom.
+            [1.0, window_aspect / self.design_aspect]
+        }
+    }
+
+    fn scale(&self) -> [f32; 2] {
+        let fit = self.fit();
+        [fit[0] * self.zoom, fit[1] * self.zoom]
+    }
+
+    /// Converts a cursor position in physical pixels to NDC.
+    pub fn window_to_ndc(&self, x: f64, y: f64) -> [f32; 2] {
+        [
+            x as f32 / self.viewport[0] * 2.0 - 1.0,
+            1.0 - y as f32 / self.viewport[1] * 2.0,
+        ]
+    }
+
+    /// Zooms by `factor`, keeping the mesh point under `anchor` (in NDC) in place.
+    pub fn zoom_around(&mut self, anchor: [f32; 2], factor: f32) {
+        let old_scale = self.scale();
+        let mesh_pt = [
+            (anchor[0] - self.pan[0]) / old_scale[0],
+            (anchor[1] - self.pan[1]) / old_scale[1],
+        ];
+
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+
+        let new_scale = self.scale();
+        self.pan = [
+            anchor[0] - mesh_pt[0] * new_scale[0],
+            anchor[1] - mesh_pt[1] * new_scale[1],
+        ];
+    }
+
+    /// Moves the view by a delta expressed in NDC.
+    pub fn pan_by(&mut self, delta: [f32; 2]) {
+        self.pan[0] += delta[0];
+        self.pan[1] += delta[1];
+    }
+
+    pub fn uniform(&self) -> CameraUniform {
+        CameraUniform {
+            scale: self.scale(),
+            offset: self.pan,
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! Dithering applied when gradients get quantized to 8 bits per channel.
+//!
+//! The same noise is used by `fs_main` in `shader.wgsl` and by [`quantize`] for images
+//! exported on the CPU, so both produce the same output for the same pixel.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DitherMode {
+    Off,
+    /// Ordered dithering with a tiled blue-noise texture.
+    BlueNoise,
+    /// Triangular-PDF white noise, built from two hashed uniform samples.
+    Triangular,
+}
+
+impl DitherMode {
+    pub fn next(self) -> Self {
+        match self {
+            DitherMode::Off => DitherMode::BlueNoise,
+            DitherMode::BlueNoise => DitherMode::Triangular,
+            DitherMode::Triangular => DitherMode::Off,
+        }
+    }
+
+    /// Must match the `switch` in `dither_offset` in `shader.wgsl`.
+    fn shader_id(self) -> u32 {
+        match self {
+            DitherMode::Off => 0,
+            DitherMode::BlueNoise => 1,
+            DitherMode::Triangular => 2,
+        }
+    }
+}
+
+impl fmt::Display for DitherMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DitherMode::Off => write!(f, "off"),
+            DitherMode::BlueNoise => write!(f, "blue noise"),
+            DitherMode::Triangular => write!(f, "triangular"),
+        }
+    }
+}
+
+pub const MAX_STRENGTH: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Dither {
+    pub mode: DitherMode,
+    /// Noise amplitude in 8-bit quantization steps.
+    pub strength: f32,
+}
+
+impl Default for Dither {
+    fn default() -> Self {
+        Self {
+            mode: DitherMode::Off,
+            strength: 1.0,
+        }
+    }
+}
+
+impl Dither {
+    pub fn adjust_strength(&mut self, delta: f32) {
+        self.strength = (self.strength + delta).clamp(0.0, MAX_STRENGTH);
+    }
+
+    /// `srgb` tells the shader that the target encodes to sRGB on store,
+    /// so noise has to be added in the encoded space to line up with the quantization steps.
+    pub fn uniform(&self, srgb: bool) -> DitherUniform {
+        DitherUniform {
+            mode: self.mode.shader_id(),
+            strength: self.strength,
+            srgb: srgb as u32,
+            _padding: 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DitherUniform {
+    mode: u32,
+    strength: f32,
+    srgb: u32,
+    _padding: u32,
+}
+
+/// Tileable blue-noise threshold map made with Ulichney's void-and-cluster method.
+pub struct BlueNoise {
+    size: usize,
+    values: Vec<u8>,
+}
+
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+
+impl BlueNoise {
+    pub fn generate(size: usize) -> Self {
+        let pixels = size * size;
+
+        // Gaussian falloff within a window around a pixel, energy further away is negligible.
+        let radius = ((3.0 * BLUE_NOISE_SIGMA).ceil() as usize).min((size - 1) / 2);
+        let window = 2 * radius + 1;
+        let kernel: Vec<f32> = (0..window * window)
+            .map(|idx| {
+                let dx = (idx % window) as f32 - radius as f32;
+                let dy = (idx / window) as f32 - radius as f32;
+                (-(dx * dx + dy * dy) / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+            })
+            .collect();
+
+        let mut pattern = vec![false; pixels];
+        let mut energy = vec![0.0; pixels];
+
+        let splat = |energy: &mut [f32], at: usize, sign: f32| {
+            let (ax, ay) = (at % size, at / size);
+
+            for (kidx, k) in kernel.iter().enumerate() {
+                // Wraps around, so the texture tiles seamlessly.
+                let x = (ax + size + kidx % window - radius) % size;
+                let y = (ay + size + kidx / window - radius) % size;
+                energy[y * size + x] += sign * k;
+            }
+        };
+
+        // Tightest cluster is the set pixel with the highest energy,
+        // largest void is the unset pixel with the lowest one.
+        let extreme = |pattern: &[bool], energy: &[f32], set: bool| {
+            (0..pixels)
+                .filter(|&idx| pattern[idx] == set)
+                .max_by(|&a, &b| {
+                    let (ea, eb) = (energy[a], energy[b]);
+                    if set {
+                        ea.total_cmp(&eb)
+                    } else {
+                        eb.total_cmp(&ea)
+                    }
+                })
+                .unwrap()
+        };
+
+        // Initial binary pattern: ~10% of pixels picked by a fixed-seed xorshift,
+        // so the texture is the same on every run.
+        let mut state = 0x2545_f491_u32;
+        let mut initial = 0;
+        while initial < pixels / 10 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+
+            let idx = state as usize % pixels;
+            if !pattern[idx] {
+                pattern[idx] = true;
+                splat(&mut energy, idx, 1.0);
+                initial += 1;
+            }
+        }
+
+        // Spread the initial pattern evenly by moving clusters into voids until it is stable.
+        loop {
+            let cluster = extreme(&pattern, &energy, true);
+            pattern[cluster] = false;
+            splat(&mut energy, cluster, -1.0);
+
+            let void = extreme(&pattern, &energy, false);
+            if void == cluster {
+                pattern[cluster] = true;
+                splat(&mut energy, cluster, 1.0);
+                break;
+            }
+
+            pattern[void] = true;
+            splat(&mut energy, void, 1.0);
+        }
+
+        let mut ranks = vec![0; pixels];
+
+        // Rank the initial pattern by removing its tightest clusters first...
+        let mut shrinking = pattern.clone();
+        let mut shrinking_energy = energy.clone();
+        for rank in (0..initial).rev() {
+            let cluster = extreme(&shrinking, &shrinking_energy, true);
+            shrinking[cluster] = false;
+            splat(&mut shrinking_energy, cluster, -1.0);
+            ranks[cluster] = rank;
+        }
+
+        // ...then fill the remaining pixels by always picking the largest void.
+        for rank in initial..pixels {
+            let void = extreme(&pattern, &energy, false);
+            pattern[void] = true;
+            splat(&mut energy, void, 1.0);
+            ranks[void] = rank;
+        }
+
+        let values = ranks
+            .into_iter()
+            .map(|rank| (rank * 256 / pixels) as u8)
+            .collect();
+
+        Self { size, values }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Raw threshold values, row by row, suitable for an `R8Unorm` texture.
+    pub fn values(&self) -> &[u8] {
+        &self.values
+    }
+
+    fn at(&self, x: u32, y: u32) -> f32 {
+        let (x, y) = (x as usize % self.size, y as usize % self.size);
+        self.values[y * self.size + x] as f32 / 255.0
+    }
+}
+
+/// PCG hash, kept bit-identical to `pcg` in `shader.wgsl`.
+fn pcg(v: u32) -> u32 {
+    let state = v.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+fn unit(hash: u32) -> f32 {
+    (hash >> 8) as f32 / 16777216.0
+}
+
+/// Noise for a pixel in quantization steps, before scaling by the strength.
+fn offset(mode: DitherMode, noise: &BlueNoise, x: u32, y: u32) -> f32 {
+    match mode {
+        DitherMode::Off => 0.0,
+        DitherMode::BlueNoise => noise.at(x, y) - 0.5,
+        DitherMode::Triangular => {
+            let seed = pcg(x ^ pcg(y));
+            unit(pcg(seed)) + unit(pcg(seed ^ 0x9e37_79b9)) - 1.0
+        }
+    }
+}
+
+/// Quantizes an already encoded color of pixel `(x, y)` to 8 bits per channel.
+pub fn quantize(color: [f32; 3], x: u32, y: u32, dither: &Dither, noise: &BlueNoise) -> [u8; 3] {
+    let offset = offset(dither.mode, noise, x, y) * dither.strength;
+
+    color.map(|c| (c * 255.0 + offset).round().clamp(0.0, 255.0) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcg_matches_the_reference_hash() {
+        // Outputs of `pcg_hash` from Jarzynski and Olano, which `shader.wgsl` has to match too.
+        for (v, hash) in [
+            (0, 129708002),
+            (1, 2831084092),
+            (42, 1223963391),
+            (u32::MAX, 3861530882),
+        ] {
+            assert_eq!(pcg(v), hash, "pcg({v})");
+        }
+    }
+
+    #[test]
+    fn quantize_without_dithering_rounds_and_clamps() {
+        let noise = BlueNoise::generate(8);
+        let dither = Dither {
+            mode: DitherMode::Off,
+            strength: 1.0,
+        };
+
+        assert_eq!(
+            quantize([0.0, 0.5, 1.0], 3, 5, &dither, &noise),
+            [0, 128, 255]
+        );
+        assert_eq!(
+            quantize([-0.2, 1.0 / 255.0, 1.5], 0, 0, &dither, &noise),
+            [0, 1, 255]
+        );
+    }
+
+    #[test]
+    fn quantize_offsets_stay_within_the_noise_amplitude() {
+        let noise = BlueNoise::generate(8);
+
+        for (mode, amplitude) in [(DitherMode::BlueNoise, 0.5), (DitherMode::Triangular, 1.0)] {
+            let dither = Dither {
+                mode,
+                strength: 1.0,
+            };
+            let mut sum = 0.0;
+
+            for y in 0..32 {
+                for x in 0..32 {
+                    let offset = offset(mode, &noise, x, y);
+                    assert!(offset.abs() <= amplitude, "{mode} offset {offset}");
+                    sum += offset;
+
+                    // Mid-gray is 127.5 steps, so the noise decides between its two neighbours.
+                    let [value, ..] = quantize([0.5; 3], x, y, &dither, &noise);
+                    assert!((127..=129).contains(&value), "{mode} gave {value}");
+                }
+            }
+
+            assert!((sum / 1024.0).abs() < 0.05, "{mode} is biased by {sum}");
+        }
+    }
+
+    #[test]
+    fn zero_strength_quantizes_like_no_dithering() {
+        let noise = BlueNoise::generate(8);
+        let color = [0.2, 0.4, 0.6];
+
+        for mode in [DitherMode::BlueNoise, DitherMode::Triangular] {
+            let dither = Dither {
+                mode,
+                strength: 0.0,
+            };
+
+            assert_eq!(quantize(color, 7, 2, &dither, &noise), [51, 102, 153]);
+        }
+    }
+}
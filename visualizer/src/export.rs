@@ -0,0 +1,196 @@
+use std::{error::Error, fs::File, io::BufWriter};
+
+use crate::dither::{self, BlueNoise, Dither};
+
+/// Offscreen captures keep more than 8 bits per channel,
+/// so quantization (and dithering) happens on the CPU while writing the image.
+pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const BYTES_PER_PIXEL: u32 = 8;
+
+pub struct Capture {
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub multisampled_view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+}
+
+impl Capture {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, sample_count: u32) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let multisampled_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Capture MSAA"),
+                size,
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format: FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            texture,
+            multisampled_view,
+            width,
+            height,
+        }
+    }
+
+    /// Copies the resolved capture back to the CPU. Expects rendering to it to be already submitted.
+    pub fn read_rgb(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<[f32; 3]> {
+        let unpadded_row = self.width * BYTES_PER_PIXEL;
+        let padded_row = unpadded_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture readback"),
+            size: (padded_row * self.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("failed to map capture buffer")
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        let data = slice.get_mapped_range();
+        let pixels = data
+            .chunks_exact(padded_row as usize)
+            .flat_map(|row| row[..unpadded_row as usize].chunks_exact(BYTES_PER_PIXEL as usize))
+            .map(|px| {
+                let channel = |c: usize| f16_to_f32(u16::from_le_bytes([px[2 * c], px[2 * c + 1]]));
+                [channel(0), channel(1), channel(2)]
+            })
+            .collect();
+
+        drop(data);
+        buffer.unmap();
+
+        pixels
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Writes captured pixels as an 8-bit PNG. `srgb` should match the swapchain,
+/// so the image looks the same as the window.
+pub fn save_png(
+    path: &str,
+    width: u32,
+    height: u32,
+    pixels: &[[f32; 3]],
+    srgb: bool,
+    dither: &Dither,
+    noise: &BlueNoise,
+) -> Result<(), Box<dyn Error>> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = pixels
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, color)| {
+            let color = if srgb {
+                color.map(|c| linear_to_srgb(c.max(0.0)))
+            } else {
+                *color
+            };
+            let (x, y) = (idx as u32 % width, idx as u32 / width);
+
+            dither::quantize(color, x, y, dither, noise)
+        })
+        .collect();
+
+    encoder.write_header()?.write_image_data(&data)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f16_to_f32_decodes_every_class() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x3555), 0.333_251_95);
+        // Largest normal, smallest subnormal.
+        assert_eq!(f16_to_f32(0x7bff), 65504.0);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(f16_to_f32(0x7e00).is_nan());
+    }
+}
@@ -1,18 +1,77 @@
+mod camera;
+mod dither;
+mod export;
+mod validate;
+mod wireframe;
+
+use camera::Camera;
+use dither::{BlueNoise, Dither, DitherMode};
 use serde::Deserialize;
-use std::{borrow::Cow, io::BufReader};
+use std::{borrow::Cow, io::BufReader, time::SystemTime};
+use validate::MeshError;
 use winit::{
-    event::{Event, WindowEvent},
+    event::{ElementState, Event, MouseButton, MouseScrollDelta, WindowEvent},
     event_loop::EventLoop,
     keyboard::Key,
     platform::modifier_supplement::KeyEventExtModifierSupplement,
     window::Window,
 };
+use wireframe::ViewMode;
 
 #[derive(Deserialize)]
 struct MeshData {
     positions: Vec<[f32; 3]>,
     colors: Vec<[f32; 3]>,
     indexes: Vec<u32>,
+    // Optional vertex attributes exported by the generator, either empty or one entry per vertex.
+    #[serde(default)]
+    uvs: Vec<[f32; 2]>,
+    #[serde(default)]
+    patch_ids: Vec<u32>,
+    #[serde(default)]
+    position_du: Vec<[f32; 3]>,
+    #[serde(default)]
+    position_dv: Vec<[f32; 3]>,
+    #[serde(default)]
+    color_du: Vec<[f32; 3]>,
+    #[serde(default)]
+    color_dv: Vec<[f32; 3]>,
+    /// Width / height ratio of the canvas the mesh was designed on.
+    #[serde(default = "default_aspect")]
+    aspect: f32,
+}
+
+fn default_aspect() -> f32 {
+    1.0
+}
+
+/// Bytes of one vertex - 3 + 3 + 2 floats, a u32 patch id and 4 * 3 floats of derivatives.
+const VERTEX_SIZE: usize = 21 * std::mem::size_of::<f32>();
+
+/// Interleaves all attributes of a vertex in the layout of `create_render_pipeline`.
+/// Optional attributes missing from the mesh file are zero.
+fn vertex_data(mesh: &MeshData) -> Vec<u8> {
+    let mut data = Vec::with_capacity(mesh.positions.len() * VERTEX_SIZE);
+
+    for (idx, (pos, color)) in mesh.positions.iter().zip(&mesh.colors).enumerate() {
+        data.extend_from_slice(bytemuck::cast_slice(pos));
+        data.extend_from_slice(bytemuck::cast_slice(color));
+        data.extend_from_slice(bytemuck::cast_slice(mesh.uvs.get(idx).unwrap_or(&[0.0; 2])));
+        data.extend_from_slice(bytemuck::bytes_of(mesh.patch_ids.get(idx).unwrap_or(&0)));
+
+        for derivative in [
+            &mesh.position_du,
+            &mesh.position_dv,
+            &mesh.color_du,
+            &mesh.color_dv,
+        ] {
+            data.extend_from_slice(bytemuck::cast_slice(
+                derivative.get(idx).unwrap_or(&[0.0; 3]),
+            ));
+        }
+    }
+
+    data
 }
 
 fn create_render_pipeline(
@@ -20,7 +79,8 @@ fn create_render_pipeline(
     pipeline_layout: &wgpu::PipelineLayout,
     shader: &wgpu::ShaderModule,
     swapchain_format: wgpu::TextureFormat,
-    polygon_mode: wgpu::PolygonMode,
+    topology: wgpu::PrimitiveTopology,
+    fragment_entry_point: &str,
 ) -> wgpu::RenderPipeline {
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: None,
@@ -29,31 +89,36 @@ fn create_render_pipeline(
             module: shader,
             entry_point: "vs_main",
             buffers: &[wgpu::VertexBufferLayout {
-                array_stride: 6 * std::mem::size_of::<f32>() as wgpu::BufferAddress,
+                array_stride: VERTEX_SIZE as wgpu::BufferAddress,
                 step_mode: wgpu::VertexStepMode::Vertex,
-                attributes: &[
-                    wgpu::VertexAttribute {
-                        format: wgpu::VertexFormat::Float32x3,
-                        offset: 0,
-                        shader_location: 0,
-                    },
-                    wgpu::VertexAttribute {
-                        format: wgpu::VertexFormat::Float32x3,
-                        offset: 3 * std::mem::size_of::<f32>() as wgpu::BufferAddress,
-                        shader_location: 1,
-                    },
+                attributes: &wgpu::vertex_attr_array![
+                    // position, color
+                    0 => Float32x3,
+                    1 => Float32x3,
+                    // uv, patch id
+                    2 => Float32x2,
+                    3 => Uint32,
+                    // position and color derivatives along u and v
+                    4 => Float32x3,
+                    5 => Float32x3,
+                    6 => Float32x3,
+                    7 => Float32x3,
                 ],
             }],
             compilation_options: Default::default(),
         },
         fragment: Some(wgpu::FragmentState {
             module: shader,
-            entry_point: "fs_main",
+            entry_point: fragment_entry_point,
             compilation_options: Default::default(),
-            targets: &[Some(swapchain_format.into())],
+            targets: &[Some(wgpu::ColorTargetState {
+                format: swapchain_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
         }),
         primitive: wgpu::PrimitiveState {
-            polygon_mode,
+            topology,
             cull_mode: Some(wgpu::Face::Back),
             ..Default::default()
         },
@@ -92,6 +157,124 @@ fn create_multisampled_framebuffer(
         .create_view(&wgpu::TextureViewDescriptor::default())
 }
 
+const BLUE_NOISE_SIZE: usize = 64;
+const DITHER_STRENGTH_STEP: f32 = 0.25;
+
+/// The window title doubles as the status line - it shows what the keys toggle, plus the
+/// outcome of the last screenshot.
+fn window_title(view_mode: ViewMode, dither: &Dither, screenshot: Option<&str>) -> String {
+    let mut title = format!("Mesh Gradient - {view_mode} - dithering: {}", dither.mode);
+
+    if dither.mode != DitherMode::Off {
+        title += &format!(" ({:.2})", dither.strength);
+    }
+
+    if let Some(screenshot) = screenshot {
+        title += &format!(" - {screenshot}");
+    }
+
+    title
+}
+
+/// Every view mode is drawn with plain triangle, line and point lists,
+/// so none of them needs `POLYGON_MODE_LINE` / `POLYGON_MODE_POINT` adapter features.
+struct Pipelines {
+    fill: wgpu::RenderPipeline,
+    edges: wgpu::RenderPipeline,
+    borders: wgpu::RenderPipeline,
+    points: wgpu::RenderPipeline,
+}
+
+impl Pipelines {
+    fn new(
+        device: &wgpu::Device,
+        pipeline_layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let create = |topology, fragment_entry_point| {
+            create_render_pipeline(
+                device,
+                pipeline_layout,
+                shader,
+                format,
+                topology,
+                fragment_entry_point,
+            )
+        };
+
+        Self {
+            fill: create(wgpu::PrimitiveTopology::TriangleList, "fs_main"),
+            edges: create(wgpu::PrimitiveTopology::LineList, "fs_edge"),
+            borders: create(wgpu::PrimitiveTopology::LineList, "fs_border"),
+            points: create(wgpu::PrimitiveTopology::PointList, "fs_main"),
+        }
+    }
+}
+
+struct MeshBuffers {
+    vertex: wgpu::Buffer,
+    vertex_count: u32,
+    index: wgpu::Buffer,
+    index_count: u32,
+    edges: wgpu::Buffer,
+    edge_index_count: u32,
+    borders: wgpu::Buffer,
+    border_index_count: u32,
+}
+
+fn draw_mesh(
+    encoder: &mut wgpu::CommandEncoder,
+    framebuf: &wgpu::TextureView,
+    resolve_target: &wgpu::TextureView,
+    pipelines: &Pipelines,
+    view_mode: ViewMode,
+    bind_groups: &[&wgpu::BindGroup],
+    buffers: &MeshBuffers,
+) {
+    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: framebuf,
+            resolve_target: Some(resolve_target),
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    rpass.set_vertex_buffer(0, buffers.vertex.slice(..));
+
+    for (idx, bind_group) in bind_groups.iter().enumerate() {
+        rpass.set_bind_group(idx as u32, bind_group, &[]);
+    }
+
+    if view_mode.draws_fill() {
+        rpass.set_pipeline(&pipelines.fill);
+        rpass.set_index_buffer(buffers.index.slice(..), wgpu::IndexFormat::Uint32);
+        rpass.draw_indexed(0..buffers.index_count, 0, 0..1);
+    }
+
+    if view_mode.draws_edges() {
+        rpass.set_pipeline(&pipelines.edges);
+        rpass.set_index_buffer(buffers.edges.slice(..), wgpu::IndexFormat::Uint32);
+        rpass.draw_indexed(0..buffers.edge_index_count, 0, 0..1);
+
+        // Borders go last, so they stay visible where they overlap interior edges.
+        rpass.set_pipeline(&pipelines.borders);
+        rpass.set_index_buffer(buffers.borders.slice(..), wgpu::IndexFormat::Uint32);
+        rpass.draw_indexed(0..buffers.border_index_count, 0, 0..1);
+    }
+
+    if view_mode == ViewMode::Points {
+        rpass.set_pipeline(&pipelines.points);
+        rpass.draw(0..buffers.vertex_count, 0..1);
+    }
+}
+
 async fn run(event_loop: EventLoop<()>, window: Window, mesh: MeshData) {
     let mut size = window.inner_size();
     size.width = size.width.max(1);
@@ -125,13 +308,9 @@ async fn run(event_loop: EventLoop<()>, window: Window, mesh: MeshData) {
         .await
         .expect("Failed to create device");
 
-    let mut buffer_contents: Vec<u8> = vec![];
+    let buffer_contents = vertex_data(&mesh);
     let mut index_buf: Vec<u8> = vec![];
 
-    for (pos, color) in mesh.positions.iter().zip(mesh.colors.iter()) {
-        buffer_contents.extend_from_slice(bytemuck::cast_slice(pos));
-        buffer_contents.extend_from_slice(bytemuck::cast_slice(color));
-    }
     index_buf.extend_from_slice(bytemuck::cast_slice(&mesh.indexes));
 
     use wgpu::util::DeviceExt;
@@ -148,31 +327,169 @@ async fn run(event_loop: EventLoop<()>, window: Window, mesh: MeshData) {
         usage: wgpu::BufferUsages::INDEX,
     });
 
+    let edges = wireframe::edges(&mesh.indexes);
+
+    let edge_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Edges"),
+        contents: bytemuck::cast_slice(&edges.interior),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+
+    let border_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Borders"),
+        contents: bytemuck::cast_slice(&edges.borders),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+
+    let buffers = MeshBuffers {
+        vertex: vertex_buffer,
+        vertex_count: mesh.positions.len() as u32,
+        index: index_buffer,
+        index_count: mesh.indexes.len() as u32,
+        edges: edge_buffer,
+        edge_index_count: edges.interior.len() as u32,
+        borders: border_buffer,
+        border_index_count: edges.borders.len() as u32,
+    };
+
     // Load the shaders from disk
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: None,
         source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
     });
 
-    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: None,
-        bind_group_layouts: &[],
-        push_constant_ranges: &[],
+    let mut camera = Camera::new(mesh.aspect, size.width, size.height);
+
+    let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Camera"),
+        contents: bytemuck::bytes_of(&camera.uniform()),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+
+    let camera_bind_group_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+    let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Camera"),
+        layout: &camera_bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: camera_buffer.as_entire_binding(),
+        }],
     });
 
     let swapchain_capabilities = surface.get_capabilities(&adapter);
     let swapchain_format = swapchain_capabilities.formats[0];
 
-    let mut polygon_mode = wgpu::PolygonMode::Fill;
+    let mut dither = Dither::default();
+    let blue_noise = BlueNoise::generate(BLUE_NOISE_SIZE);
+
+    let blue_noise_view = device
+        .create_texture_with_data(
+            &queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Blue noise"),
+                size: wgpu::Extent3d {
+                    width: blue_noise.size() as u32,
+                    height: blue_noise.size() as u32,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            blue_noise.values(),
+        )
+        .create_view(&wgpu::TextureViewDescriptor::default());
+
+    let dither_bind_group_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Dither"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+    let create_dither_bind_group = |uniform: dither::DitherUniform| {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Dither"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Dither"),
+            layout: &dither_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&blue_noise_view),
+                },
+            ],
+        });
+
+        (buffer, bind_group)
+    };
 
-    let mut render_pipeline = create_render_pipeline(
-        &device,
-        &pipeline_layout,
-        &shader,
-        swapchain_format,
-        polygon_mode,
+    let (dither_buffer, dither_bind_group) =
+        create_dither_bind_group(dither.uniform(swapchain_format.is_srgb()));
+    // Exports are dithered on the CPU while quantizing, so the GPU must not add noise on top.
+    let (_, export_dither_bind_group) = create_dither_bind_group(
+        Dither {
+            mode: dither::DitherMode::Off,
+            ..dither
+        }
+        .uniform(false),
     );
 
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&camera_bind_group_layout, &dither_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    let mut view_mode = ViewMode::Fill;
+    let pipelines = Pipelines::new(&device, &pipeline_layout, &shader, swapchain_format);
+
     let mut config = surface
         .get_default_config(&adapter, size.width, size.height)
         .unwrap();
@@ -182,7 +499,13 @@ async fn run(event_loop: EventLoop<()>, window: Window, mesh: MeshData) {
 
     let mut framebuf = create_multisampled_framebuffer(&device, &config, 4);
 
+    let mut cursor_ndc = [0.0, 0.0];
+    let mut dragging = false;
+    // Outcome of the last "s" press, until the next one.
+    let mut screenshot: Option<String> = None;
+
     let window = &window;
+    window.set_title(&window_title(view_mode, &dither, None));
     event_loop
         .run(move |event, target| {
             // Have the closure take ownership of the resources.
@@ -203,31 +526,129 @@ async fn run(event_loop: EventLoop<()>, window: Window, mesh: MeshData) {
 
                         surface.configure(&device, &config);
                         framebuf = create_multisampled_framebuffer(&device, &config, 4);
+                        camera.resize(config.width, config.height);
                         // On macos the window needs to be redrawn manually after resizing
                         window.request_redraw();
                     }
-                    WindowEvent::KeyboardInput { event, .. } => {
-                        if event.state == winit::event::ElementState::Released
-                            && event.key_without_modifiers().as_ref() == Key::Character("w")
-                        {
-                            polygon_mode = match polygon_mode {
-                                wgpu::PolygonMode::Fill => wgpu::PolygonMode::Line,
-                                wgpu::PolygonMode::Line => wgpu::PolygonMode::Point,
-                                wgpu::PolygonMode::Point => wgpu::PolygonMode::Fill,
-                            };
-
-                            render_pipeline = create_render_pipeline(
-                                &device,
-                                &pipeline_layout,
-                                &shader,
-                                swapchain_format,
-                                polygon_mode,
-                            );
+                    WindowEvent::KeyboardInput { event, .. }
+                        if event.state == ElementState::Released =>
+                    {
+                        match event.key_without_modifiers().as_ref() {
+                            Key::Character("w") => {
+                                view_mode = view_mode.next();
+                            }
+                            Key::Character("a") => camera.keep_aspect = !camera.keep_aspect,
+                            Key::Character("r") => camera.reset(),
+                            Key::Character("d") => {
+                                dither.mode = dither.mode.next();
+                            }
+                            Key::Character("[") => {
+                                dither.adjust_strength(-DITHER_STRENGTH_STEP);
+                            }
+                            Key::Character("]") => {
+                                dither.adjust_strength(DITHER_STRENGTH_STEP);
+                            }
+                            Key::Character("s") => {
+                                let capture =
+                                    export::Capture::new(&device, config.width, config.height, 4);
+                                let pipelines = Pipelines::new(
+                                    &device,
+                                    &pipeline_layout,
+                                    &shader,
+                                    export::FORMAT,
+                                );
+
+                                queue.write_buffer(
+                                    &camera_buffer,
+                                    0,
+                                    bytemuck::bytes_of(&camera.uniform()),
+                                );
+
+                                let mut encoder = device.create_command_encoder(
+                                    &wgpu::CommandEncoderDescriptor { label: None },
+                                );
+                                draw_mesh(
+                                    &mut encoder,
+                                    &capture.multisampled_view,
+                                    &capture.view,
+                                    &pipelines,
+                                    view_mode,
+                                    &[&camera_bind_group, &export_dither_bind_group],
+                                    &buffers,
+                                );
+                                queue.submit(Some(encoder.finish()));
+
+                                let pixels = capture.read_rgb(&device, &queue);
+                                let path = format!(
+                                    "screenshot-{}.png",
+                                    SystemTime::now()
+                                        .duration_since(SystemTime::UNIX_EPOCH)
+                                        .unwrap()
+                                        .as_secs()
+                                );
+
+                                screenshot = Some(
+                                    match export::save_png(
+                                        &path,
+                                        capture.width(),
+                                        capture.height(),
+                                        &pixels,
+                                        swapchain_format.is_srgb(),
+                                        &dither,
+                                        &blue_noise,
+                                    ) {
+                                        Ok(()) => format!("saved {path}"),
+                                        Err(err) => format!("saving {path} failed: {err}"),
+                                    },
+                                );
+                            }
+                            _ => return,
+                        }
 
+                        window.set_title(&window_title(view_mode, &dither, screenshot.as_deref()));
+                        window.request_redraw();
+                    }
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        let lines = match delta {
+                            MouseScrollDelta::LineDelta(_, y) => y,
+                            MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / 50.0,
+                        };
+
+                        camera.zoom_around(cursor_ndc, 1.1_f32.powf(lines));
+                        window.request_redraw();
+                    }
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Left,
+                        ..
+                    } => {
+                        dragging = state == ElementState::Pressed;
+                    }
+                    WindowEvent::CursorMoved { position, .. } => {
+                        let new_cursor = camera.window_to_ndc(position.x, position.y);
+
+                        if dragging {
+                            camera.pan_by([
+                                new_cursor[0] - cursor_ndc[0],
+                                new_cursor[1] - cursor_ndc[1],
+                            ]);
                             window.request_redraw();
                         }
+
+                        cursor_ndc = new_cursor;
                     }
                     WindowEvent::RedrawRequested => {
+                        queue.write_buffer(
+                            &camera_buffer,
+                            0,
+                            bytemuck::bytes_of(&camera.uniform()),
+                        );
+                        queue.write_buffer(
+                            &dither_buffer,
+                            0,
+                            bytemuck::bytes_of(&dither.uniform(swapchain_format.is_srgb())),
+                        );
+
                         let frame = surface
                             .get_current_texture()
                             .expect("Failed to acquire next swap chain texture");
@@ -238,31 +659,15 @@ async fn run(event_loop: EventLoop<()>, window: Window, mesh: MeshData) {
                             device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
                                 label: None,
                             });
-                        {
-                            let mut rpass =
-                                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                                    label: None,
-                                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                                        view: &framebuf,
-                                        resolve_target: Some(&view),
-                                        ops: wgpu::Operations {
-                                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                                            store: wgpu::StoreOp::Store,
-                                        },
-                                    })],
-                                    depth_stencil_attachment: None,
-                                    timestamp_writes: None,
-                                    occlusion_query_set: None,
-                                });
-                            rpass.set_index_buffer(
-                                index_buffer.slice(..),
-                                wgpu::IndexFormat::Uint32,
-                            );
-                            rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
-
-                            rpass.set_pipeline(&render_pipeline);
-                            rpass.draw_indexed(0..(mesh.indexes.len() as u32), 0, 0..1);
-                        }
+                        draw_mesh(
+                            &mut encoder,
+                            &framebuf,
+                            &view,
+                            &pipelines,
+                            view_mode,
+                            &[&camera_bind_group, &dither_bind_group],
+                            &buffers,
+                        );
 
                         queue.submit(Some(encoder.finish()));
                         frame.present();
@@ -275,22 +680,53 @@ async fn run(event_loop: EventLoop<()>, window: Window, mesh: MeshData) {
         .unwrap();
 }
 
+fn load_mesh(fname: &str) -> MeshData {
+    serde_json::from_reader(BufReader::new(
+        std::fs::File::open(fname).expect("failed to open file"),
+    ))
+    .expect("failed to parse json from file")
+}
+
+/// Prints validation diagnostics to stderr. Returns whether the mesh is safe to upload.
+fn report_validation(fname: &str, mesh: &MeshData) -> bool {
+    let errors = validate::validate(mesh);
+
+    for error in &errors {
+        let severity = if error.is_fatal() { "error" } else { "warning" };
+        eprintln!("{fname}: {severity}: {error}");
+    }
+
+    !errors.iter().any(MeshError::is_fatal)
+}
+
 pub fn main() {
-    let mesh = {
-        let mut args = std::env::args();
-        let cmd = args.next().unwrap();
-        let fname = args
-            .next()
-            .unwrap_or_else(|| panic!("Usage: {cmd} <path-to-mesh-file>"));
-
-        let value: MeshData = serde_json::from_reader(BufReader::new(
-            std::fs::File::open(fname).expect("failed to open file"),
-        ))
-        .expect("failed to parse json from file");
-
-        value
+    let args: Vec<String> = std::env::args().collect();
+
+    let (check_only, fname) = match args.as_slice() {
+        [_, flag, fname] if flag == "--check" => (true, fname.as_str()),
+        [_, fname] => (false, fname.as_str()),
+        _ => panic!("Usage: {} [--check] <path-to-mesh-file>", args[0]),
     };
 
+    let mesh = load_mesh(fname);
+    let valid = report_validation(fname, &mesh);
+
+    if check_only {
+        if valid {
+            println!(
+                "{fname}: ok ({} vertices, {} triangles)",
+                mesh.positions.len(),
+                mesh.indexes.len() / 3
+            );
+        }
+
+        std::process::exit(if valid { 0 } else { 1 });
+    }
+
+    if !valid {
+        std::process::exit(1);
+    }
+
     let event_loop = EventLoop::new().unwrap();
     #[allow(unused_mut)]
     let mut builder =
@@ -300,3 +736,73 @@ pub fn main() {
 
     pollster::block_on(run(event_loop, window, mesh));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh(vertex_count: usize) -> MeshData {
+        MeshData {
+            positions: vec![[1.0, 2.0, 3.0]; vertex_count],
+            colors: vec![[0.25, 0.5, 0.75]; vertex_count],
+            indexes: vec![],
+            uvs: vec![],
+            patch_ids: vec![],
+            position_du: vec![],
+            position_dv: vec![],
+            color_du: vec![],
+            color_dv: vec![],
+            aspect: 1.0,
+        }
+    }
+
+    /// The floats of vertex `idx`, with the patch id as its bits.
+    fn vertex(data: &[u8], idx: usize) -> Vec<f32> {
+        data[idx * VERTEX_SIZE..(idx + 1) * VERTEX_SIZE]
+            .chunks_exact(4)
+            .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn vertex_data_has_a_vertex_size_per_vertex() {
+        for vertex_count in [0, 1, 5] {
+            assert_eq!(
+                vertex_data(&mesh(vertex_count)).len(),
+                vertex_count * VERTEX_SIZE
+            );
+        }
+    }
+
+    #[test]
+    fn missing_attributes_are_zeros() {
+        let data = vertex_data(&mesh(2));
+
+        for idx in 0..2 {
+            let vertex = vertex(&data, idx);
+
+            assert_eq!(vertex[..6], [1.0, 2.0, 3.0, 0.25, 0.5, 0.75]);
+            assert!(vertex[6..].iter().all(|&value| value.to_bits() == 0));
+        }
+    }
+
+    #[test]
+    fn present_attributes_follow_the_pipeline_layout() {
+        let mut mesh = mesh(1);
+        mesh.uvs = vec![[0.5, 1.0]];
+        mesh.patch_ids = vec![7];
+        mesh.position_du = vec![[1.0; 3]];
+        mesh.position_dv = vec![[2.0; 3]];
+        mesh.color_du = vec![[3.0; 3]];
+        mesh.color_dv = vec![[4.0; 3]];
+
+        let vertex = vertex(&vertex_data(&mesh), 0);
+
+        assert_eq!(vertex[6..8], [0.5, 1.0]);
+        assert_eq!(vertex[8].to_bits(), 7);
+        assert_eq!(vertex[9..12], [1.0; 3]);
+        assert_eq!(vertex[12..15], [2.0; 3]);
+        assert_eq!(vertex[15..18], [3.0; 3]);
+        assert_eq!(vertex[18..21], [4.0; 3]);
+    }
+}
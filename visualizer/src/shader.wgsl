@@ -1,6 +1,36 @@
+struct Camera {
+    scale: vec2<f32>,
+    offset: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct Dither {
+    // 0 - off, 1 - blue noise, 2 - triangular PDF
+    mode: u32,
+    // noise amplitude in 8-bit quantization steps
+    strength: f32,
+    // 1 if the target encodes to sRGB on store
+    srgb: u32,
+    _padding: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> dither: Dither;
+@group(1) @binding(1)
+var blue_noise: texture_2d<f32>;
+
 struct VertexIn {
     @location(0) pos: vec3<f32>,
     @location(1) color: vec3<f32>,
+    // Optional attributes, zero when the mesh file doesn't have them.
+    @location(2) uv: vec2<f32>,
+    @location(3) patch_id: u32,
+    @location(4) position_du: vec3<f32>,
+    @location(5) position_dv: vec3<f32>,
+    @location(6) color_du: vec3<f32>,
+    @location(7) color_dv: vec3<f32>,
 };
 
 struct VertexOut {
@@ -11,13 +41,69 @@ struct VertexOut {
 @vertex
 fn vs_main(in: VertexIn) -> VertexOut {
     var out: VertexOut;
-    out.position = vec4<f32>(in.pos, 1.0);
+    out.position = vec4<f32>(in.pos.xy * camera.scale + camera.offset, in.pos.z, 1.0);
     out.color = vec4<f32>(in.color, 1.0);
 
     return out;
 }
 
+// Kept bit-identical to `pcg` in dither.rs.
+fn pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn unit(hash: u32) -> f32 {
+    return f32(hash >> 8u) / 16777216.0;
+}
+
+fn dither_offset(pixel: vec2<u32>) -> f32 {
+    switch dither.mode {
+        case 1u: {
+            let size = textureDimensions(blue_noise);
+            return textureLoad(blue_noise, pixel % size, 0).r - 0.5;
+        }
+        case 2u: {
+            let seed = pcg(pixel.x ^ pcg(pixel.y));
+            return unit(pcg(seed)) + unit(pcg(seed ^ 0x9e3779b9u)) - 1.0;
+        }
+        default: {
+            return 0.0;
+        }
+    }
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
 @fragment
 fn fs_main(out: VertexOut) -> @location(0) vec4<f32> {
-    return out.color;
+    if dither.mode == 0u {
+        return out.color;
+    }
+
+    let offset = dither_offset(vec2<u32>(out.position.xy)) * dither.strength / 255.0;
+
+    if dither.srgb == 1u {
+        let encoded = clamp(linear_to_srgb(max(out.color.rgb, vec3<f32>(0.0))) + offset, vec3<f32>(0.0), vec3<f32>(1.0));
+        return vec4<f32>(srgb_to_linear(encoded), out.color.a);
+    }
+
+    return vec4<f32>(out.color.rgb + offset, out.color.a);
+}
+
+@fragment
+fn fs_edge(out: VertexOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 1.0, 0.35);
+}
+
+@fragment
+fn fs_border(out: VertexOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.85, 0.2, 1.0);
 }
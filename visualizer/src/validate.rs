@@ -0,0 +1,437 @@
+use std::fmt;
+
+use crate::MeshData;
+
+/// Triangles with a smaller (doubled) area than this are reported as degenerate.
+const DEGENERATE_AREA_EPSILON: f32 = 1e-12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    Position,
+    Color,
+    Uv,
+    PatchId,
+    PositionDu,
+    PositionDv,
+    ColorDu,
+    ColorDv,
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attribute::Position => write!(f, "position"),
+            Attribute::Color => write!(f, "color"),
+            Attribute::Uv => write!(f, "uv"),
+            Attribute::PatchId => write!(f, "patch id"),
+            Attribute::PositionDu => write!(f, "position derivative along u"),
+            Attribute::PositionDv => write!(f, "position derivative along v"),
+            Attribute::ColorDu => write!(f, "color derivative along u"),
+            Attribute::ColorDv => write!(f, "color derivative along v"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshError {
+    /// The design aspect ratio is zero, negative or not finite, so the mesh can't be letterboxed.
+    InvalidAspect {
+        aspect: f32,
+    },
+    LengthMismatch {
+        positions: usize,
+        colors: usize,
+    },
+    /// An optional attribute which is present, but not for every vertex.
+    OptionalLengthMismatch {
+        attribute: Attribute,
+        count: usize,
+        vertex_count: usize,
+    },
+    IndexCountNotTriangles {
+        count: usize,
+    },
+    IndexOutOfBounds {
+        triangle: usize,
+        index: u32,
+        vertex_count: usize,
+    },
+    NonFinite {
+        attribute: Attribute,
+        vertex: usize,
+        value: Vec<f32>,
+    },
+    DegenerateTriangle {
+        triangle: usize,
+        indexes: [u32; 3],
+    },
+}
+
+impl MeshError {
+    /// Whether the mesh can't be uploaded to the GPU with this error present.
+    /// Degenerate triangles render as nothing, so they are only worth a warning.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, MeshError::DegenerateTriangle { .. })
+    }
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::InvalidAspect { aspect } => {
+                write!(f, "`aspect` is {aspect}, but it must be a positive number")
+            }
+            MeshError::LengthMismatch { positions, colors } => write!(
+                f,
+                "`positions` has {positions} entries but `colors` has {colors}"
+            ),
+            MeshError::OptionalLengthMismatch {
+                attribute,
+                count,
+                vertex_count,
+            } => write!(
+                f,
+                "{count} vertices have a {attribute}, but there are {vertex_count} vertices"
+            ),
+            MeshError::IndexCountNotTriangles { count } => write!(
+                f,
+                "`indexes` has {count} entries, which is not divisible by 3"
+            ),
+            MeshError::IndexOutOfBounds {
+                triangle,
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "triangle {triangle} references vertex {index}, but there are only {vertex_count} vertices"
+            ),
+            MeshError::NonFinite {
+                attribute,
+                vertex,
+                value,
+            } => write!(f, "vertex {vertex} has non-finite {attribute} {value:?}"),
+            MeshError::DegenerateTriangle { triangle, indexes } => write!(
+                f,
+                "triangle {triangle} (vertices {indexes:?}) is degenerate"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// Checks mesh data before it gets uploaded to the GPU. Returns every problem found,
+/// in the order of: aspect, attribute lengths, index count, non-finite values and per-triangle problems.
+pub fn validate(mesh: &MeshData) -> Vec<MeshError> {
+    let mut errors = vec![];
+
+    // NaN fails the comparison too.
+    if !(mesh.aspect.is_finite() && mesh.aspect > 0.0) {
+        errors.push(MeshError::InvalidAspect {
+            aspect: mesh.aspect,
+        });
+    }
+
+    if mesh.positions.len() != mesh.colors.len() {
+        errors.push(MeshError::LengthMismatch {
+            positions: mesh.positions.len(),
+            colors: mesh.colors.len(),
+        });
+    }
+
+    // Only vertices with both attributes present end up in the vertex buffer.
+    let vertex_count = mesh.positions.len().min(mesh.colors.len());
+
+    for (attribute, count) in [
+        (Attribute::Uv, mesh.uvs.len()),
+        (Attribute::PatchId, mesh.patch_ids.len()),
+        (Attribute::PositionDu, mesh.position_du.len()),
+        (Attribute::PositionDv, mesh.position_dv.len()),
+        (Attribute::ColorDu, mesh.color_du.len()),
+        (Attribute::ColorDv, mesh.color_dv.len()),
+    ] {
+        if count != 0 && count != vertex_count {
+            errors.push(MeshError::OptionalLengthMismatch {
+                attribute,
+                count,
+                vertex_count,
+            });
+        }
+    }
+
+    if mesh.indexes.len() % 3 != 0 {
+        errors.push(MeshError::IndexCountNotTriangles {
+            count: mesh.indexes.len(),
+        });
+    }
+
+    let uvs = mesh.uvs.iter().map(|uv| uv.as_slice());
+    let vectors = [
+        (Attribute::Position, &mesh.positions),
+        (Attribute::Color, &mesh.colors),
+        (Attribute::PositionDu, &mesh.position_du),
+        (Attribute::PositionDv, &mesh.position_dv),
+        (Attribute::ColorDu, &mesh.color_du),
+        (Attribute::ColorDv, &mesh.color_dv),
+    ]
+    .map(|(attribute, values)| (attribute, values.iter().map(|value| value.as_slice())));
+
+    for (attribute, values) in vectors {
+        check_finite(&mut errors, attribute, values);
+    }
+    check_finite(&mut errors, Attribute::Uv, uvs);
+
+    for (triangle, indexes) in mesh.indexes.chunks_exact(3).enumerate() {
+        let indexes = [indexes[0], indexes[1], indexes[2]];
+        let mut in_bounds = true;
+
+        for index in indexes {
+            if index as usize >= vertex_count {
+                in_bounds = false;
+                errors.push(MeshError::IndexOutOfBounds {
+                    triangle,
+                    index,
+                    vertex_count,
+                });
+            }
+        }
+
+        if in_bounds && is_degenerate(mesh, indexes) {
+            errors.push(MeshError::DegenerateTriangle { triangle, indexes });
+        }
+    }
+
+    errors
+}
+
+fn check_finite<'a>(
+    errors: &mut Vec<MeshError>,
+    attribute: Attribute,
+    values: impl Iterator<Item = &'a [f32]>,
+) {
+    for (vertex, value) in values.enumerate() {
+        if value.iter().any(|c| !c.is_finite()) {
+            errors.push(MeshError::NonFinite {
+                attribute,
+                vertex,
+                value: value.to_vec(),
+            });
+        }
+    }
+}
+
+fn is_degenerate(mesh: &MeshData, [a, b, c]: [u32; 3]) -> bool {
+    if a == b || b == c || a == c {
+        return true;
+    }
+
+    let [a, b, c] = [a, b, c].map(|i| mesh.positions[i as usize]);
+    let doubled_area = (b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1]);
+
+    // NaN areas are already reported as non-finite positions.
+    doubled_area.abs() < DEGENERATE_AREA_EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle(aspect: f32) -> MeshData {
+        MeshData {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            colors: vec![[1.0, 0.0, 0.0]; 3],
+            indexes: vec![0, 1, 2],
+            uvs: vec![],
+            patch_ids: vec![],
+            position_du: vec![],
+            position_dv: vec![],
+            color_du: vec![],
+            color_dv: vec![],
+            aspect,
+        }
+    }
+
+    #[test]
+    fn positive_aspect_is_valid() {
+        assert_eq!(validate(&triangle(1.0)), vec![]);
+        assert_eq!(validate(&triangle(16.0 / 9.0)), vec![]);
+    }
+
+    #[test]
+    fn invalid_aspect_is_fatal() {
+        for aspect in [0.0, -1.0, f32::INFINITY, f32::NAN] {
+            let errors = validate(&triangle(aspect));
+
+            assert_eq!(errors.len(), 1, "aspect {aspect}");
+            assert!(matches!(errors[0], MeshError::InvalidAspect { .. }));
+            assert!(errors[0].is_fatal());
+        }
+    }
+
+    #[test]
+    fn mismatched_lengths_are_fatal() {
+        let mut mesh = triangle(1.0);
+        mesh.colors.pop();
+
+        let errors = validate(&mesh);
+
+        assert_eq!(
+            errors[0],
+            MeshError::LengthMismatch {
+                positions: 3,
+                colors: 2
+            }
+        );
+        assert!(errors[0].is_fatal());
+    }
+
+    #[test]
+    fn index_count_must_be_divisible_by_3() {
+        let mut mesh = triangle(1.0);
+        mesh.indexes.push(0);
+
+        let errors = validate(&mesh);
+
+        assert_eq!(errors, vec![MeshError::IndexCountNotTriangles { count: 4 }]);
+        assert!(errors[0].is_fatal());
+    }
+
+    #[test]
+    fn out_of_range_indexes_are_fatal() {
+        let mut mesh = triangle(1.0);
+        mesh.indexes = vec![0, 3, 7];
+
+        let errors = validate(&mesh);
+
+        assert_eq!(
+            errors,
+            [3, 7].map(|index| MeshError::IndexOutOfBounds {
+                triangle: 0,
+                index,
+                vertex_count: 3
+            })
+        );
+        assert!(errors.iter().all(MeshError::is_fatal));
+    }
+
+    #[test]
+    fn non_finite_values_are_fatal() {
+        let mut mesh = triangle(1.0);
+        mesh.positions[1][0] = f32::NAN;
+        mesh.colors[2][1] = f32::INFINITY;
+
+        let errors = validate(&mesh);
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            MeshError::NonFinite {
+                attribute: Attribute::Position,
+                vertex: 1,
+                ..
+            }
+        ));
+        assert_eq!(
+            errors[1],
+            MeshError::NonFinite {
+                attribute: Attribute::Color,
+                vertex: 2,
+                value: vec![1.0, f32::INFINITY, 0.0]
+            }
+        );
+        assert!(errors.iter().all(MeshError::is_fatal));
+    }
+
+    #[test]
+    fn optional_attributes_are_absent_or_complete() {
+        let mut mesh = triangle(1.0);
+        mesh.uvs = vec![[0.0, 0.0]; 3];
+        mesh.patch_ids = vec![0; 3];
+        assert_eq!(validate(&mesh), vec![]);
+
+        mesh.patch_ids.pop();
+        mesh.color_dv = vec![[0.0; 3]; 4];
+
+        let errors = validate(&mesh);
+
+        assert_eq!(
+            errors,
+            vec![
+                MeshError::OptionalLengthMismatch {
+                    attribute: Attribute::PatchId,
+                    count: 2,
+                    vertex_count: 3
+                },
+                MeshError::OptionalLengthMismatch {
+                    attribute: Attribute::ColorDv,
+                    count: 4,
+                    vertex_count: 3
+                },
+            ]
+        );
+        assert!(errors.iter().all(MeshError::is_fatal));
+    }
+
+    #[test]
+    fn every_optional_attribute_must_be_finite() {
+        let mut mesh = triangle(1.0);
+        mesh.uvs = vec![[0.0, 0.0]; 3];
+        mesh.position_du = vec![[0.0; 3]; 3];
+        mesh.position_dv = vec![[0.0; 3]; 3];
+        mesh.color_du = vec![[0.0; 3]; 3];
+        mesh.color_dv = vec![[0.0; 3]; 3];
+
+        mesh.uvs[0][1] = f32::NAN;
+        mesh.position_du[1][2] = f32::INFINITY;
+        mesh.position_dv[2][0] = f32::NEG_INFINITY;
+        mesh.color_du[0][0] = f32::NAN;
+        mesh.color_dv[1][1] = f32::INFINITY;
+
+        let attributes: Vec<_> = validate(&mesh)
+            .into_iter()
+            .map(|error| match error {
+                MeshError::NonFinite {
+                    attribute, vertex, ..
+                } => (attribute, vertex),
+                error => panic!("unexpected {error}"),
+            })
+            .collect();
+
+        assert_eq!(
+            attributes,
+            vec![
+                (Attribute::PositionDu, 1),
+                (Attribute::PositionDv, 2),
+                (Attribute::ColorDu, 0),
+                (Attribute::ColorDv, 1),
+                (Attribute::Uv, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn degenerate_triangles_are_warnings() {
+        let mut mesh = triangle(1.0);
+        mesh.positions.push([2.0, 0.0, 0.0]);
+        mesh.colors.push([0.0, 0.0, 1.0]);
+        // Collinear points, then a repeated index.
+        mesh.indexes = vec![0, 1, 2, 0, 1, 3, 2, 2, 1];
+
+        let errors = validate(&mesh);
+
+        assert_eq!(
+            errors,
+            vec![
+                MeshError::DegenerateTriangle {
+                    triangle: 1,
+                    indexes: [0, 1, 3]
+                },
+                MeshError::DegenerateTriangle {
+                    triangle: 2,
+                    indexes: [2, 2, 1]
+                },
+            ]
+        );
+        assert!(!errors.iter().any(MeshError::is_fatal));
+    }
+}
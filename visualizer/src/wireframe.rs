@@ -0,0 +1,93 @@
+use std::{collections::BTreeMap, fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    Fill,
+    /// Filled mesh with its edges drawn on top.
+    Overlay,
+    Wireframe,
+    Points,
+}
+
+impl ViewMode {
+    pub fn next(self) -> Self {
+        match self {
+            ViewMode::Fill => ViewMode::Overlay,
+            ViewMode::Overlay => ViewMode::Wireframe,
+            ViewMode::Wireframe => ViewMode::Points,
+            ViewMode::Points => ViewMode::Fill,
+        }
+    }
+
+    pub fn draws_fill(self) -> bool {
+        matches!(self, ViewMode::Fill | ViewMode::Overlay)
+    }
+
+    pub fn draws_edges(self) -> bool {
+        matches!(self, ViewMode::Overlay | ViewMode::Wireframe)
+    }
+}
+
+impl fmt::Display for ViewMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViewMode::Fill => write!(f, "fill"),
+            ViewMode::Overlay => write!(f, "fill + wireframe"),
+            ViewMode::Wireframe => write!(f, "wireframe"),
+            ViewMode::Points => write!(f, "point cloud"),
+        }
+    }
+}
+
+/// Line list indexes of the mesh edges, each edge listed once.
+pub struct Edges {
+    pub interior: Vec<u32>,
+    /// Edges used by a single triangle. `generator` doesn't share vertices between patches,
+    /// so these are exactly the patch borders.
+    pub borders: Vec<u32>,
+}
+
+pub fn edges(indexes: &[u32]) -> Edges {
+    let mut triangle_count: BTreeMap<(u32, u32), u32> = BTreeMap::new();
+
+    for triangle in indexes.chunks_exact(3) {
+        for (a, b) in [
+            (triangle[0], triangle[1]),
+            (triangle[1], triangle[2]),
+            (triangle[2], triangle[0]),
+        ] {
+            *triangle_count.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+
+    let mut interior = vec![];
+    let mut borders = vec![];
+
+    for ((a, b), count) in triangle_count {
+        let list = if count == 1 {
+            &mut borders
+        } else {
+            &mut interior
+        };
+
+        list.extend_from_slice(&[a, b]);
+    }
+
+    Edges { interior, borders }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quad_has_one_interior_edge_and_four_borders() {
+        // 0 - 1
+        // | / |
+        // 2 - 3
+        let edges = edges(&[0, 2, 1, 1, 2, 3]);
+
+        assert_eq!(edges.interior, vec![1, 2]);
+        assert_eq!(edges.borders, vec![0, 1, 0, 2, 1, 3, 2, 3]);
+    }
+}